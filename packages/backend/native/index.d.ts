@@ -1,9 +1,37 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/**
+ * Chunks of a document parsed in the background, which parses at most a few
+ * chunks ahead of the ones read.
+ */
+export declare class DocStream {
+  /**
+   * Returns the next chunk, or `null` once the document is exhausted or the
+   * stream was aborted.
+   */
+  next(): Promise<DocChunk | null>
+  /**
+   * Stops parsing the document, which is best-effort: the parser notices
+   * the abort between the pages, files and windows it reads, so a single
+   * page being parsed is finished first.
+   */
+  abort(): void
+}
+
 export declare class Tokenizer {
   count(content: string, allowedSpecial?: Array<string> | undefined | null): number
 }
 
+export interface DocChunk {
+  index: number
+  content: string
+  /** SHA3-256 of the content before it is cleaned up */
+  hash: string
+  start?: number
+  end?: number
+  metadata: Record<string, any>
+}
+
 export declare function fromModelName(modelName: string): Tokenizer | null
 
 export declare function getMime(input: Uint8Array): string
@@ -18,6 +46,56 @@ export declare function mergeUpdatesInApplyWay(updates: Array<Buffer>): Buffer
 
 export declare function mintChallengeResponse(resource: string, bits?: number | undefined | null): Promise<string>
 
-export declare function parseDoc(filePath: string, doc: Buffer): Promise<{ name: string, chunks: Array<{index: number, content: string}> }>
+export declare function parseDoc(filePath: string, doc: Buffer, options?: ParseDocOptions | undefined | null): Promise<{ name: string, hash: string, chunks: Array<{index: number, content: string, hash: string, start?: number, end?: number, metadata: Record<string, any>}>, metadata: { title?: string, author?: string, subject?: string, keywords?: string, created?: string, modified?: string, language?: string, encoding?: string } }>
+
+/**
+ * Parses the file at `file_path` like `parse_doc`, without reading it into
+ * memory up front or collecting all of its chunks.
+ */
+export declare function parseDocStream(filePath: string, options?: ParseDocOptions | undefined | null): DocStream
+
+export interface ParseDocOptions {
+  /**
+   * Password used to open encrypted documents, which fail with
+   * `DOC_PASSWORD_REQUIRED` without one and `DOC_INCORRECT_PASSWORD` with a
+   * wrong one
+   */
+  password?: string
+  /** Maximum number of tokens of a chunk, defaults to 7168 */
+  chunkSize?: number
+  /** Number of tokens shared by consecutive chunks, defaults to 128 */
+  chunkOverlap?: number
+  /** Tokenizer chunk sizes are measured with, defaults to `cl100k_base` */
+  encoding?: 'o200k_base' | 'cl100k_base' | 'p50k_base' | 'r50k_base' | 'p50k_edit' | 'gpt2'
+  /**
+   * Number of lines from which source code is split along functions and
+   * classes, defaults to 1000
+   */
+  codeThreshold?: number
+  /** Splitter used for every document instead of the one of its format */
+  splitter?: 'token' | 'markdown' | 'sentence' | 'table' | 'structured' | 'transcript'
+  /**
+   * Milliseconds after which parsing fails with `DOC_PARSE_TIMEOUT`, even
+   * while the parser is stuck in a single page
+   */
+  timeout?: number
+  /**
+   * Bytes of extracted text after which parsing fails with
+   * `DOC_TEXT_TOO_LARGE`
+   */
+  maxTextSize?: number
+  /** Number of chunks after which parsing fails with `DOC_TOO_MANY_CHUNKS` */
+  maxChunks?: number
+  /**
+   * Similarity from which chunks repeating an earlier chunk are dropped,
+   * above 0 and at most 1, such as 0.9
+   */
+  dedupThreshold?: number
+  /**
+   * Whether running headers, footers and page numbers are removed from PDF
+   * pages, defaults to `true`
+   */
+  stripPdfHeaders?: boolean
+}
 
 export declare function verifyChallengeResponse(response: string, bits: number, resource: string): Promise<boolean>
@@ -13,3 +13,5 @@ export const Tokenizer = binding.Tokenizer;
 export const fromModelName = binding.fromModelName;
 export const htmlSanitize = binding.htmlSanitize;
 export const parseDoc = binding.parseDoc;
+export const parseDocStream = binding.parseDocStream;
+export const DocStream = binding.DocStream;
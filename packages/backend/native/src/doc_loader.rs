@@ -1,10 +1,70 @@
-use affine_common::doc_loader::Doc;
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{sync_channel, RecvTimeoutError},
+    Arc,
+  },
+  time::Duration,
+};
+
+use affine_common::doc_loader::{Chunk, Doc, DocOptions, LoaderError, SplitterKind};
 use napi::{
   anyhow::anyhow,
   bindgen_prelude::{AsyncTask, Buffer},
-  Env, JsObject, Result, Task,
+  Env, JsError, JsObject, Result, Task,
+};
+use serde_json::Value;
+use tokio::sync::{
+  mpsc::{self, Receiver},
+  Mutex,
 };
 
+/// Number of chunks parsed ahead of the chunks read from a `DocStream`
+const STREAM_BUFFER: usize = 16;
+
+/// Turns errors of the doc loader into JS errors, whose `code` tells the
+/// parse limits and encrypted documents apart from other failures.
+fn to_napi_error(e: LoaderError) -> napi::Error<&'static str> {
+  let code = match e {
+    LoaderError::Timeout(_) => "DOC_PARSE_TIMEOUT",
+    LoaderError::TextTooLarge(_) => "DOC_TEXT_TOO_LARGE",
+    LoaderError::TooManyChunks(_) => "DOC_TOO_MANY_CHUNKS",
+    LoaderError::PasswordRequired => "DOC_PASSWORD_REQUIRED",
+    LoaderError::IncorrectPassword => "DOC_INCORRECT_PASSWORD",
+    _ => "GenericFailure",
+  };
+  napi::Error::new(code, e.to_string())
+}
+
+/// Returns the JS error of `e`, keeping its code where tasks only
+/// return errors with a `Status`.
+fn to_js_error(env: Env, e: LoaderError) -> napi::Error {
+  JsError::from(to_napi_error(e)).into_unknown(env).into()
+}
+
+/// Runs `parse` on a thread of its own, failing with `LoaderError::Timeout`
+/// once `timeout` passed even while the parser is stuck in a single page or
+/// element, which it only checks its deadline between. The parser keeps its
+/// thread until it returns.
+fn with_watchdog<T: Send + 'static>(
+  timeout: Option<Duration>,
+  parse: impl FnOnce() -> std::result::Result<T, LoaderError> + Send + 'static,
+) -> std::result::Result<T, LoaderError> {
+  let Some(timeout) = timeout else {
+    return parse();
+  };
+  let (sender, receiver) = sync_channel(1);
+  std::thread::spawn(move || {
+    let _ = sender.send(parse());
+  });
+  match receiver.recv_timeout(timeout) {
+    Ok(result) => result,
+    Err(RecvTimeoutError::Timeout) => Err(LoaderError::Timeout(timeout)),
+    Err(RecvTimeoutError::Disconnected) => Err(LoaderError::Other("Parser panicked".into())),
+  }
+}
+
 pub struct Document {
   inner: Doc,
 }
@@ -22,45 +82,318 @@ impl Document {
       let mut obj = env.create_object()?;
       obj.set_named_property("index", i as i64)?;
       obj.set_named_property("content", content)?;
+      obj.set_named_property("hash", chunk.hash())?;
+      if let (Some(start), Some(end)) = (chunk.start, chunk.end) {
+        obj.set_named_property("start", start as i64)?;
+        obj.set_named_property("end", end as i64)?;
+      }
+      obj.set_named_property("metadata", env.to_js_value(&chunk.metadata)?)?;
       array.set_element(i as u32, obj)?;
     }
     Ok(array)
   }
 
+  fn metadata(&self, env: Env) -> Result<JsObject> {
+    let metadata = &self.inner.metadata;
+    let mut obj = env.create_object()?;
+    for (key, value) in [
+      ("title", &metadata.title),
+      ("author", &metadata.author),
+      ("subject", &metadata.subject),
+      ("keywords", &metadata.keywords),
+      ("created", &metadata.created),
+      ("modified", &metadata.modified),
+      ("language", &metadata.language),
+      ("encoding", &metadata.encoding),
+    ] {
+      if let Some(value) = value {
+        obj.set_named_property(key, value.as_str())?;
+      }
+    }
+    Ok(obj)
+  }
+
   fn resolve(self, env: Env) -> Result<JsObject> {
     let mut obj = env.create_object()?;
     obj.set_named_property("name", self.name())?;
+    obj.set_named_property("hash", self.inner.hash())?;
     obj.set_named_property("chunks", self.chunks(env)?)?;
+    obj.set_named_property("metadata", self.metadata(env)?)?;
     Ok(obj)
   }
 }
 
+#[napi(object)]
+#[derive(Default)]
+pub struct ParseDocOptions {
+  /// Password used to open encrypted documents, which fail with
+  /// `DOC_PASSWORD_REQUIRED` without one and `DOC_INCORRECT_PASSWORD` with a
+  /// wrong one
+  pub password: Option<String>,
+  /// Maximum number of tokens of a chunk, defaults to 7168
+  pub chunk_size: Option<u32>,
+  /// Number of tokens shared by consecutive chunks, defaults to 128
+  pub chunk_overlap: Option<u32>,
+  /// Tokenizer chunk sizes are measured with, defaults to `cl100k_base`
+  #[napi(
+    ts_type = "'o200k_base' | 'cl100k_base' | 'p50k_base' | 'r50k_base' | 'p50k_edit' | 'gpt2'"
+  )]
+  pub encoding: Option<String>,
+  /// Number of lines from which source code is split along functions and
+  /// classes, defaults to 1000
+  pub code_threshold: Option<u32>,
+  /// Splitter used for every document instead of the one of its format
+  #[napi(ts_type = "'token' | 'markdown' | 'sentence' | 'table' | 'structured' | 'transcript'")]
+  pub splitter: Option<String>,
+  /// Milliseconds after which parsing fails with `DOC_PARSE_TIMEOUT`, even
+  /// while the parser is stuck in a single page
+  pub timeout: Option<u32>,
+  /// Bytes of extracted text after which parsing fails with
+  /// `DOC_TEXT_TOO_LARGE`
+  pub max_text_size: Option<u32>,
+  /// Number of chunks after which parsing fails with `DOC_TOO_MANY_CHUNKS`
+  pub max_chunks: Option<u32>,
+  /// Similarity from which chunks repeating an earlier chunk are dropped,
+  /// above 0 and at most 1, such as 0.9
+  pub dedup_threshold: Option<f64>,
+  /// Whether running headers, footers and page numbers are removed from PDF
+  /// pages, defaults to `true`
+  pub strip_pdf_headers: Option<bool>,
+}
+
+fn timeout(options: &ParseDocOptions) -> Option<Duration> {
+  options
+    .timeout
+    .map(|timeout| Duration::from_millis(timeout as u64))
+}
+
+impl TryFrom<ParseDocOptions> for DocOptions {
+  type Error = napi::Error;
+
+  fn try_from(options: ParseDocOptions) -> Result<Self> {
+    let mut doc_options = DocOptions::default();
+    if let Some(password) = options.password {
+      doc_options = doc_options.with_password(password);
+    }
+    if let Some(chunk_size) = options.chunk_size {
+      doc_options = doc_options.with_chunk_size(chunk_size as usize);
+    }
+    if let Some(chunk_overlap) = options.chunk_overlap {
+      doc_options = doc_options.with_chunk_overlap(chunk_overlap as usize);
+    }
+    if let Some(encoding) = options.encoding {
+      doc_options = doc_options.with_encoding_name(&encoding);
+    }
+    if let Some(code_threshold) = options.code_threshold {
+      doc_options = doc_options.with_code_threshold(code_threshold as u64);
+    }
+    if let Some(splitter) = options.splitter {
+      let splitter = SplitterKind::from_name(&splitter)
+        .ok_or_else(|| anyhow!("Unknown splitter: {}", splitter))?;
+      doc_options = doc_options.with_splitter(splitter);
+    }
+    if let Some(timeout) = timeout(&options) {
+      doc_options = doc_options.with_timeout(timeout);
+    }
+    if let Some(max_text_size) = options.max_text_size {
+      doc_options = doc_options.with_max_text_size(max_text_size as usize);
+    }
+    if let Some(max_chunks) = options.max_chunks {
+      doc_options = doc_options.with_max_chunks(max_chunks as usize);
+    }
+    if let Some(dedup_threshold) = options.dedup_threshold {
+      doc_options = doc_options
+        .with_dedup_threshold(dedup_threshold)
+        .map_err(|e| anyhow!(e))?;
+    }
+    if let Some(strip_pdf_headers) = options.strip_pdf_headers {
+      doc_options = doc_options.with_strip_pdf_headers(strip_pdf_headers);
+    }
+    Ok(doc_options)
+  }
+}
+
 pub struct AsyncParseDocResponse {
   file_path: String,
   doc: Vec<u8>,
+  options: DocOptions,
+  timeout: Option<Duration>,
 }
 
 #[napi]
 impl Task for AsyncParseDocResponse {
-  type Output = Document;
+  type Output = std::result::Result<Document, LoaderError>;
   type JsValue = JsObject;
 
   fn compute(&mut self) -> Result<Self::Output> {
-    let doc = Doc::new(&self.file_path, &self.doc).map_err(|e| anyhow!(e))?;
-    Ok(Document { inner: doc })
+    let file_path = self.file_path.clone();
+    let doc = std::mem::take(&mut self.doc);
+    let options = self.options.clone();
+    let doc = with_watchdog(self.timeout, move || {
+      Doc::with_options(&file_path, &doc, options)
+    });
+    Ok(doc.map(|inner| Document { inner }))
   }
 
-  fn resolve(&mut self, env: Env, doc: Document) -> Result<Self::JsValue> {
-    doc.resolve(env)
+  fn resolve(&mut self, env: Env, doc: Self::Output) -> Result<Self::JsValue> {
+    doc.map_err(|e| to_js_error(env, e))?.resolve(env)
   }
 }
 
 #[napi(
-  ts_return_type = "Promise<{ name: string, chunks: Array<{index: number, content: string}> }>"
+  ts_return_type = "Promise<{ name: string, hash: string, chunks: Array<{index: number, content: string, hash: string, start?: number, end?: number, metadata: Record<string, any>}>, metadata: { title?: string, author?: string, subject?: string, keywords?: string, created?: string, modified?: string, language?: string, encoding?: string } }>"
 )]
-pub fn parse_doc(file_path: String, doc: Buffer) -> AsyncTask<AsyncParseDocResponse> {
-  AsyncTask::new(AsyncParseDocResponse {
+pub fn parse_doc(
+  file_path: String,
+  doc: Buffer,
+  options: Option<ParseDocOptions>,
+) -> Result<AsyncTask<AsyncParseDocResponse>> {
+  let options = options.unwrap_or_default();
+  Ok(AsyncTask::new(AsyncParseDocResponse {
     file_path,
     doc: doc.to_vec(),
-  })
+    timeout: timeout(&options),
+    options: options.try_into()?,
+  }))
+}
+
+#[napi(object)]
+pub struct DocChunk {
+  pub index: u32,
+  pub content: String,
+  /// SHA3-256 of the content before it is cleaned up
+  pub hash: String,
+  pub start: Option<i64>,
+  pub end: Option<i64>,
+  #[napi(ts_type = "Record<string, any>")]
+  pub metadata: HashMap<String, Value>,
+}
+
+impl From<Chunk> for DocChunk {
+  fn from(chunk: Chunk) -> Self {
+    Self {
+      index: chunk.index as u32,
+      content: crate::utils::clean_content(&chunk.content),
+      hash: chunk.hash(),
+      start: chunk.start.map(|start| start as i64),
+      end: chunk.end.map(|end| end as i64),
+      metadata: chunk.metadata,
+    }
+  }
+}
+
+type ChunkReceiver = Arc<Mutex<Receiver<std::result::Result<Chunk, LoaderError>>>>;
+
+/// Chunks of a document parsed in the background, which parses at most a few
+/// chunks ahead of the ones read.
+#[napi]
+pub struct DocStream {
+  receiver: ChunkReceiver,
+  aborted: Arc<AtomicBool>,
+}
+
+pub struct AsyncNextDocChunk {
+  receiver: ChunkReceiver,
+  aborted: Arc<AtomicBool>,
+}
+
+#[napi]
+impl Task for AsyncNextDocChunk {
+  type Output = Option<std::result::Result<Chunk, LoaderError>>;
+  type JsValue = Option<DocChunk>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut receiver = self.receiver.blocking_lock();
+    if !self.aborted.load(Ordering::Relaxed) {
+      let chunk = receiver.blocking_recv();
+      if !self.aborted.load(Ordering::Relaxed) {
+        return Ok(chunk);
+      }
+    }
+    // `abort` cannot close the receiver while a `next` holds it, so the
+    // `next` closes it instead, which stops a parser waiting for room in the
+    // buffer
+    receiver.close();
+    Ok(None)
+  }
+
+  fn resolve(&mut self, env: Env, chunk: Self::Output) -> Result<Self::JsValue> {
+    match chunk {
+      Some(Ok(chunk)) => Ok(Some(chunk.into())),
+      Some(Err(e)) => Err(to_js_error(env, e)),
+      None => Ok(None),
+    }
+  }
+}
+
+#[napi]
+impl DocStream {
+  /// Returns the next chunk, or `null` once the document is exhausted or the
+  /// stream was aborted.
+  #[napi(ts_return_type = "Promise<DocChunk | null>")]
+  pub fn next(&self) -> AsyncTask<AsyncNextDocChunk> {
+    AsyncTask::new(AsyncNextDocChunk {
+      receiver: self.receiver.clone(),
+      aborted: self.aborted.clone(),
+    })
+  }
+
+  /// Stops parsing the document, which is best-effort: the parser notices
+  /// the abort between the pages, files and windows it reads, so a single
+  /// page being parsed is finished first.
+  #[napi]
+  pub fn abort(&self) {
+    self.aborted.store(true, Ordering::Relaxed);
+    // a pending `next` holds the receiver and closes it once it returns
+    if let Ok(mut receiver) = self.receiver.try_lock() {
+      receiver.close();
+    }
+  }
+}
+
+/// Parses the file at `file_path` like `parse_doc`, without reading it into
+/// memory up front or collecting all of its chunks.
+#[napi]
+pub fn parse_doc_stream(file_path: String, options: Option<ParseDocOptions>) -> Result<DocStream> {
+  let options = options.unwrap_or_default();
+  let timeout = timeout(&options);
+  let aborted = Arc::new(AtomicBool::new(false));
+  let options = DocOptions::try_from(options)?.with_abort(aborted.clone());
+  let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+  let stream = DocStream {
+    receiver: Arc::new(Mutex::new(receiver)),
+    aborted: aborted.clone(),
+  };
+
+  // fails the stream once the timeout passed, even while the parser is stuck
+  // in a single page or element
+  let timed_out = Arc::new(AtomicBool::new(false));
+  let (done, finished) = sync_channel::<()>(0);
+  if let Some(timeout) = timeout {
+    let (sender, timed_out) = (sender.clone(), timed_out.clone());
+    std::thread::spawn(move || {
+      if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+        timed_out.store(true, Ordering::Relaxed);
+        let _ = sender.blocking_send(Err(LoaderError::Timeout(timeout)));
+      }
+    });
+  }
+
+  std::thread::spawn(move || {
+    // dropped once parsing ended, which stops the watchdog
+    let _done = done;
+    let result = Doc::stream(&file_path, options, |chunk| {
+      !aborted.load(Ordering::Relaxed)
+        && !timed_out.load(Ordering::Relaxed)
+        && sender.blocking_send(Ok(chunk)).is_ok()
+    });
+    // a timeout is reported by the watchdog already, and an aborted stream
+    // returns no more chunks
+    if let Err(e) = result {
+      if !timed_out.load(Ordering::Relaxed) && !aborted.load(Ordering::Relaxed) {
+        let _ = sender.blocking_send(Err(e));
+      }
+    }
+  });
+  Ok(stream)
 }
@@ -1,5 +1,7 @@
 import { createRequire } from 'node:module';
 
+import type { ParseDocOptions } from '@affine/server-native';
+
 let serverNativeModule: typeof import('@affine/server-native');
 try {
   serverNativeModule = await import('@affine/server-native');
@@ -30,7 +32,34 @@ export const mintChallengeResponse = async (resource: string, bits: number) => {
 };
 
 export const getMime = serverNativeModule.getMime;
+
+// errors of the parse limits and of encrypted documents carry a `DOC_*`
+// code, such as `DOC_PARSE_TIMEOUT`
 export const parseDoc = serverNativeModule.parseDoc;
+
+/**
+ * Yields the chunks of the file at `filePath` while it is being parsed,
+ * aborting `signal` stops the parser.
+ */
+export async function* parseDocStream(
+  filePath: string,
+  options?: ParseDocOptions,
+  signal?: AbortSignal
+) {
+  const stream = serverNativeModule.parseDocStream(filePath, options);
+  const abort = () => stream.abort();
+  signal?.addEventListener('abort', abort, { once: true });
+  try {
+    let chunk;
+    while (!signal?.aborted && (chunk = await stream.next())) {
+      yield chunk;
+    }
+  } finally {
+    signal?.removeEventListener('abort', abort);
+    stream.abort();
+  }
+}
+
 export const Tokenizer = serverNativeModule.Tokenizer;
 export const fromModelName = serverNativeModule.fromModelName;
 export const htmlSanitize = serverNativeModule.htmlSanitize;
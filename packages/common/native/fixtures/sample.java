@@ -0,0 +1,7 @@
+import java.util.List;
+
+public class Sample {
+    public static void main(String[] args) {
+        System.out.println("Hello, World!");
+    }
+}
@@ -0,0 +1,5 @@
+import kotlin.math.max
+
+fun main() {
+    println("Hello, World! ${max(1, 2)}")
+}
@@ -0,0 +1,7 @@
+import scala.math.max
+
+object Sample {
+  def main(args: Array[String]): Unit = {
+    println(s"Hello, World! ${max(1, 2)}")
+  }
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+/// Number of hashes of the MinHash signature of a chunk
+const NUM_HASHES: usize = 32;
+/// The signature is cut into bands, chunks sharing a band are compared
+const BANDS: usize = 8;
+const ROWS: usize = NUM_HASHES / BANDS;
+/// Number of consecutive words compared between chunks
+const SHINGLE_WORDS: usize = 3;
+
+/// FNV-1a, which unlike the hasher of the standard library is stable.
+fn fnv(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
+
+/// Derives the `seed`th hash function from `hash` (splitmix64).
+fn mix(hash: u64, seed: u64) -> u64 {
+  let mut z = hash ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+/// Returns the words of `text` in lower case without punctuation.
+fn words(text: &str) -> Vec<String> {
+  text
+    .split_whitespace()
+    .map(|word| {
+      word
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+    })
+    .filter(|word| !word.is_empty())
+    .collect()
+}
+
+/// Replaces every number of `word` by `0`, so that chunks differing only by
+/// page numbers or dates are near-identical.
+fn mask_numbers(word: &str) -> String {
+  let mut masked = String::new();
+  for c in word.chars() {
+    if !c.is_numeric() {
+      masked.push(c);
+    } else if !masked.ends_with('0') {
+      masked.push('0');
+    }
+  }
+  masked
+}
+
+fn shingles(words: &[String]) -> HashSet<u64> {
+  let words = words
+    .iter()
+    .map(|word| mask_numbers(word))
+    .collect::<Vec<_>>();
+  words
+    .windows(SHINGLE_WORDS.min(words.len()))
+    .map(|shingle| fnv(shingle.join(" ").as_bytes()))
+    .collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+  let intersection = a.intersection(b).count();
+  intersection as f64 / (a.len() + b.len() - intersection) as f64
+}
+
+/// Finds chunks that are near-identical to a chunk seen before, like the
+/// headers of every page or repeated licence blocks.
+///
+/// Chunks are near-identical if the Jaccard similarity of their sets of
+/// three consecutive words, whose numbers are not told apart, is at least
+/// `threshold`, or if their words are all the same for a `threshold` of 1.
+/// Candidates are found by locality-sensitive hashing of MinHash signatures,
+/// which misses a growing share of pairs below a similarity of 0.8.
+pub struct Deduplicator {
+  threshold: f64,
+  exact: HashSet<u64>,
+  kept: Vec<HashSet<u64>>,
+  bands: HashMap<(usize, u64), Vec<usize>>,
+}
+
+impl Deduplicator {
+  pub fn new(threshold: f64) -> Self {
+    Self {
+      threshold,
+      exact: HashSet::new(),
+      kept: vec![],
+      bands: HashMap::new(),
+    }
+  }
+
+  /// Returns whether `text` is near-identical to a text seen before, texts
+  /// that are not are remembered.
+  pub fn is_duplicate(&mut self, text: &str) -> bool {
+    let words = words(text);
+    if words.is_empty() {
+      return false;
+    }
+    if !self.exact.insert(fnv(words.join(" ").as_bytes())) {
+      return true;
+    }
+    if self.threshold >= 1.0 {
+      return false;
+    }
+
+    let shingles = shingles(&words);
+    let signature = (0..NUM_HASHES as u64)
+      .map(|seed| {
+        shingles
+          .iter()
+          .map(|shingle| mix(*shingle, seed))
+          .min()
+          .unwrap_or_default()
+      })
+      .collect::<Vec<_>>();
+    let keys = signature
+      .chunks(ROWS)
+      .enumerate()
+      .map(|(band, rows)| {
+        let bytes = rows
+          .iter()
+          .flat_map(|row| row.to_le_bytes())
+          .collect::<Vec<_>>();
+        (band, fnv(&bytes))
+      })
+      .collect::<Vec<_>>();
+
+    let mut candidates = keys
+      .iter()
+      .filter_map(|key| self.bands.get(key))
+      .flatten()
+      .copied()
+      .collect::<Vec<_>>();
+    candidates.sort_unstable();
+    candidates.dedup();
+    if candidates
+      .into_iter()
+      .any(|candidate| jaccard(&shingles, &self.kept[candidate]) >= self.threshold)
+    {
+      return true;
+    }
+
+    let index = self.kept.len();
+    self.kept.push(shingles);
+    for key in keys {
+      self.bands.entry(key).or_default().push(index);
+    }
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_deduplicator() {
+    let licence = "Permission is hereby granted, free of charge, to any person obtaining a \
+                   copy of this software and associated documentation files, to deal in the \
+                   Software without restriction, including without limitation the rights to \
+                   use, copy, modify, merge, publish, distribute, sublicense, and/or sell \
+                   copies of the Software.";
+
+    let mut dedup = Deduplicator::new(1.0);
+    assert!(!dedup.is_duplicate("ACME Corp. Annual Report - Page 1"));
+    assert!(dedup.is_duplicate("acme corp annual report page 1"));
+    assert!(!dedup.is_duplicate("acme corp annual report page 12"));
+    assert!(!dedup.is_duplicate(licence));
+    assert!(dedup.is_duplicate(&licence.to_uppercase()));
+    assert!(!dedup.is_duplicate(&licence.replace("sell", "rent")));
+    assert!(!dedup.is_duplicate("   "));
+    assert!(!dedup.is_duplicate("   "));
+
+    let mut dedup = Deduplicator::new(0.8);
+    assert!(!dedup.is_duplicate(licence));
+    assert!(dedup.is_duplicate(&licence.replace("sell", "rent")));
+    assert!(!dedup.is_duplicate("An unrelated paragraph about the quarterly results."));
+    assert!(!dedup.is_duplicate("ACME Corp. Annual Report - Page 1"));
+    assert!(dedup.is_duplicate("acme corp annual report page 12"));
+  }
+}
@@ -1,10 +1,19 @@
 use std::{
-  io::Cursor,
+  collections::HashMap,
+  fs::File,
+  io::{BufReader, Cursor, Read, Seek},
   panic::{catch_unwind, AssertUnwindSafe},
   path::PathBuf,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Duration,
 };
 
 use path_ext::PathExt;
+use serde_json::Value;
+use sha3::{Digest, Sha3_256};
 
 use super::*;
 
@@ -12,25 +21,411 @@ use super::*;
 pub struct Chunk {
   pub index: usize,
   pub content: String,
+  /// Byte range of the chunk in the text extracted from the document
   pub start: Option<usize>,
   pub end: Option<usize>,
+  /// Loader specific metadata, such as the `page_number` of PDF chunks
+  pub metadata: HashMap<String, Value>,
 }
 
+impl Chunk {
+  /// Returns the SHA3-256 of the content as hex, which stays the same as
+  /// long as the content does.
+  pub fn hash(&self) -> String {
+    format!("{:x}", Sha3_256::digest(self.content.as_bytes()))
+  }
+
+  fn new(index: usize, document: Document) -> Self {
+    let mut metadata = document.metadata;
+    let mut offset = |key: &str| {
+      metadata
+        .remove(key)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+    };
+    Chunk {
+      index,
+      start: offset("start"),
+      end: offset("end"),
+      content: document.page_content,
+      metadata,
+    }
+  }
+}
+
+/// Splitters cutting the text of documents into chunks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitterKind {
+  /// Chunks of `chunk_size` tokens, cut along lines and words
+  Token,
+  /// Chunks along markdown sections, tagged with their `heading_path`
+  Markdown,
+  /// Chunks of whole paragraphs and sentences, no format uses it unless
+  /// selected with [`DocOptions::with_splitter`] or the loader registry
+  Sentence,
+  /// Groups of rows of markdown tables, repeating the header row
+  Table,
+  /// Objects and arrays of JSON, YAML and TOML files
+  Structured,
+  /// Cues of subtitles and transcripts, merged with their timestamps
+  Transcript,
+}
+
+impl SplitterKind {
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name.to_lowercase().as_str() {
+      "token" => Some(Self::Token),
+      "markdown" => Some(Self::Markdown),
+      "sentence" => Some(Self::Sentence),
+      "table" => Some(Self::Table),
+      "structured" => Some(Self::Structured),
+      "transcript" => Some(Self::Transcript),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone)]
 pub struct DocOptions {
   code_threshold: u64,
+  splitter_options: SplitterOptions,
+  /// Splitter used for every document instead of the one of its format
+  splitter: Option<SplitterKind>,
+  /// Number of bytes of text files read at once by `Doc::stream`
+  stream_window_size: usize,
+  password: Option<String>,
+  max_archive_entries: usize,
+  max_archive_size: u64,
+  max_archive_depth: usize,
+  /// Budget of the outermost archive while loading the files of an archive
+  archive_budget: Option<Arc<ArchiveBudget>>,
+  archive_depth: usize,
+  /// Whether the document is a file embedded in another one, whose name was
+  /// chosen by the uploader rather than being a path to detect its type from
+  embedded: bool,
+  timeout: Option<Duration>,
+  /// Deadline of the outermost document, shared by the documents embedded
+  /// in it
+  deadline: Option<Deadline>,
+  /// Set once parsing is to stop, checked along with the deadline
+  abort: Option<Arc<AtomicBool>>,
+  max_text_size: Option<usize>,
+  max_chunks: Option<usize>,
+  /// Registry of the loaders of the embedder, the global one if unset
+  registry: Option<Arc<LoaderRegistry>>,
+  /// Splitter the registry sets for the format of the document being loaded
+  format_splitter: Option<SplitterKind>,
+  dedup_threshold: Option<f64>,
+  strip_pdf_headers: bool,
 }
 
 impl Default for DocOptions {
   fn default() -> Self {
     Self {
       code_threshold: 1000,
+      splitter_options: SplitterOptions::default(),
+      splitter: None,
+      stream_window_size: 1024 * 1024,
+      password: None,
+      max_archive_entries: 1000,
+      max_archive_size: 100 * 1024 * 1024,
+      max_archive_depth: 3,
+      archive_budget: None,
+      archive_depth: 0,
+      embedded: false,
+      timeout: None,
+      deadline: None,
+      abort: None,
+      max_text_size: None,
+      max_chunks: None,
+      registry: None,
+      format_splitter: None,
+      dedup_threshold: None,
+      strip_pdf_headers: true,
+    }
+  }
+}
+
+impl DocOptions {
+  pub fn with_code_threshold(mut self, code_threshold: u64) -> Self {
+    self.code_threshold = code_threshold;
+    self
+  }
+
+  /// Maximum number of tokens of a chunk.
+  pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+    self.splitter_options = self.splitter_options.with_chunk_size(chunk_size);
+    self
+  }
+
+  /// Number of tokens shared by consecutive chunks.
+  pub fn with_chunk_overlap(mut self, chunk_overlap: usize) -> Self {
+    self.splitter_options = self.splitter_options.with_chunk_overlap(chunk_overlap);
+    self
+  }
+
+  /// Tokenizer the chunk size is measured with, such as `cl100k_base` or
+  /// `o200k_base`.
+  pub fn with_encoding_name(mut self, encoding_name: &str) -> Self {
+    self.splitter_options = self.splitter_options.with_encoding_name(encoding_name);
+    self
+  }
+
+  /// Splits every document with `splitter` rather than the splitter picked
+  /// for its format.
+  pub fn with_splitter(mut self, splitter: SplitterKind) -> Self {
+    self.splitter = Some(splitter);
+    self
+  }
+
+  /// Number of bytes of plain text files read and split at once while
+  /// streaming them, chunks never span two windows.
+  pub fn with_stream_window_size(mut self, stream_window_size: usize) -> Self {
+    self.stream_window_size = stream_window_size;
+    self
+  }
+
+  /// Password used to open encrypted documents.
+  pub fn with_password<S: Into<String>>(mut self, password: S) -> Self {
+    self.password = Some(password.into());
+    self
+  }
+
+  /// Maximum number of files read from an archive, including the files of
+  /// archives nested in it.
+  pub fn with_max_archive_entries(mut self, max_archive_entries: usize) -> Self {
+    self.max_archive_entries = max_archive_entries;
+    self
+  }
+
+  /// Maximum number of uncompressed bytes read from an archive, including the
+  /// archives nested in it.
+  pub fn with_max_archive_size(mut self, max_archive_size: u64) -> Self {
+    self.max_archive_size = max_archive_size;
+    self
+  }
+
+  /// Maximum number of archives nested in each other.
+  pub fn with_max_archive_depth(mut self, max_archive_depth: usize) -> Self {
+    self.max_archive_depth = max_archive_depth;
+    self
+  }
+
+  /// Maximum time a document may take to parse, including the documents
+  /// embedded in it. The deadline is checked between the pages, files and
+  /// windows of a document, a single page that takes longer is parsed to its
+  /// end before parsing fails. Callers that need a hard limit run the parser
+  /// on a thread of its own and stop waiting for it at the deadline.
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Stops parsing with [`LoaderError::Aborted`] once `abort` is set, which is
+  /// checked like the deadline between the pages, files and windows of a
+  /// document.
+  pub fn with_abort(mut self, abort: Arc<AtomicBool>) -> Self {
+    self.abort = Some(abort);
+    self
+  }
+
+  /// Maximum number of bytes of text extracted from a document.
+  pub fn with_max_text_size(mut self, max_text_size: usize) -> Self {
+    self.max_text_size = Some(max_text_size);
+    self
+  }
+
+  /// Maximum number of chunks of a document.
+  pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+    self.max_chunks = Some(max_chunks);
+    self
+  }
+
+  /// Loads documents with the loaders and splitters of `registry` instead of
+  /// the ones of [`LoaderRegistry::global`].
+  pub fn with_registry(mut self, registry: Arc<LoaderRegistry>) -> Self {
+    self.registry = Some(registry);
+    self
+  }
+
+  /// Drops chunks that are near-identical to an earlier chunk of the same
+  /// document, such as the headers and footers of pages or repeated licence
+  /// blocks. `threshold` is the Jaccard similarity of the sets of three
+  /// consecutive words of two chunks from which they are near-identical,
+  /// between 0 exclusive and 1. 1.0 only drops chunks that differ in case and
+  /// punctuation alone, lower thresholds also take numbers for the same, such
+  /// as page numbers and dates. Thresholds below 0.8 miss more and more
+  /// near-identical chunks. Fails with
+  /// [`LoaderError::InvalidDedupThreshold`] outside of that range.
+  pub fn with_dedup_threshold(mut self, threshold: f64) -> LoaderResult<Self> {
+    // written so that NaN is rejected too
+    let valid = threshold > 0.0 && threshold <= 1.0;
+    if !valid {
+      return Err(LoaderError::InvalidDedupThreshold(threshold));
+    }
+    self.dedup_threshold = Some(threshold);
+    Ok(self)
+  }
+
+  /// Whether running headers, footers and page numbers are removed from the
+  /// text of PDF pages, which is the default.
+  pub fn with_strip_pdf_headers(mut self, strip_pdf_headers: bool) -> Self {
+    self.strip_pdf_headers = strip_pdf_headers;
+    self
+  }
+
+  /// Returns the options of the files embedded in a document, which are one
+  /// level deeper than it and share the budget of the outermost archive.
+  /// Their type is detected from their content alone.
+  fn nested(&self) -> LoaderResult<(Arc<ArchiveBudget>, DocOptions)> {
+    if self.archive_depth >= self.max_archive_depth {
+      return Err(LoaderError::ArchiveTooDeep(self.max_archive_depth));
+    }
+    let budget = self.archive_budget.clone().unwrap_or_else(|| {
+      Arc::new(ArchiveBudget::new(
+        self.max_archive_entries,
+        self.max_archive_size,
+      ))
+    });
+    let options = DocOptions {
+      archive_budget: Some(budget.clone()),
+      archive_depth: self.archive_depth + 1,
+      embedded: true,
+      ..self.clone()
+    };
+    Ok((budget, options))
+  }
+
+  fn deduplicator(&self) -> Option<Deduplicator> {
+    self.dedup_threshold.map(Deduplicator::new)
+  }
+
+  fn check_deadline(&self) -> LoaderResult<()> {
+    if let Some(abort) = &self.abort {
+      if abort.load(Ordering::Relaxed) {
+        return Err(LoaderError::Aborted);
+      }
+    }
+    match &self.deadline {
+      Some(deadline) => deadline.check(),
+      None => Ok(()),
+    }
+  }
+
+  fn check_text_size(&self, text_size: usize) -> LoaderResult<()> {
+    match self.max_text_size {
+      Some(max_text_size) if text_size > max_text_size => {
+        Err(LoaderError::TextTooLarge(max_text_size))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  fn check_chunk_count(&self, chunk_count: usize) -> LoaderResult<()> {
+    match self.max_chunks {
+      Some(max_chunks) if chunk_count > max_chunks => Err(LoaderError::TooManyChunks(max_chunks)),
+      _ => Ok(()),
+    }
+  }
+
+  /// Returns the splitter used instead of `kind`, the splitter of the format.
+  fn splitter_kind(&self, kind: SplitterKind) -> SplitterKind {
+    self.splitter.or(self.format_splitter).unwrap_or(kind)
+  }
+
+  fn registry(&self) -> Arc<LoaderRegistry> {
+    self.registry.clone().unwrap_or_else(LoaderRegistry::global)
+  }
+
+  /// Returns the splitter for documents whose format is split with `kind`.
+  fn splitter(&self, kind: SplitterKind) -> Box<dyn TextSplitter> {
+    let splitter_options = self.splitter_options.clone();
+    match self.splitter_kind(kind) {
+      SplitterKind::Token => Box::new(TokenSplitter::new(splitter_options)),
+      SplitterKind::Markdown => Box::new(MarkdownSplitter::new(splitter_options)),
+      SplitterKind::Sentence => Box::new(SentenceSplitter::new(splitter_options)),
+      SplitterKind::Table => Box::new(TableSplitter::new(splitter_options)),
+      SplitterKind::Structured => Box::new(StructuredSplitter::new(splitter_options)),
+      SplitterKind::Transcript => Box::new(TranscriptSplitter::new(splitter_options)),
     }
   }
 }
 
+/// Runs `f`, turning panics of the parsers into errors.
+fn catch_panic<T>(f: impl FnOnce() -> LoaderResult<T>) -> LoaderResult<T> {
+  catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+    LoaderError::Other(match e.downcast::<String>() {
+      Ok(v) => *v,
+      Err(e) => match e.downcast::<&str>() {
+        Ok(v) => v.to_string(),
+        _ => "Unknown Source of Error".to_owned(),
+      },
+    })
+  })?
+}
+
+/// Detects the language of the text at the start of the document, returns
+/// its ISO 639-1 code if the detection is reliable.
+fn detect_language(chunks: &[Chunk]) -> Option<String> {
+  let mut sample = String::new();
+  for chunk in chunks {
+    if sample.len() >= 4096 {
+      break;
+    }
+    sample.push_str(&chunk.content);
+    sample.push('\n');
+  }
+  let info = whatlang::detect(&sample)?;
+  info
+    .is_reliable()
+    .then(|| iso_639_1(info.lang()).to_string())
+}
+
+/// Splits the parts of a document streamed by [`Doc::stream`], such as the
+/// windows of a text file, and hands their chunks on.
+struct ChunkStream<'a, F> {
+  options: &'a DocOptions,
+  splitter: Box<dyn TextSplitter>,
+  dedup: Option<Deduplicator>,
+  index: usize,
+  on_chunk: F,
+}
+
+impl<'a, F: FnMut(Chunk) -> bool> ChunkStream<'a, F> {
+  fn new(options: &'a DocOptions, splitter: SplitterKind, on_chunk: F) -> Self {
+    Self {
+      options,
+      splitter: options.splitter(splitter),
+      dedup: options.deduplicator(),
+      index: 0,
+      on_chunk,
+    }
+  }
+
+  /// Splits the next part, returns `false` once `on_chunk` asked to stop.
+  fn push(&mut self, document: Document) -> LoaderResult<bool> {
+    for d in self.splitter.split_documents(&[document])? {
+      if self
+        .dedup
+        .as_mut()
+        .is_some_and(|dedup| dedup.is_duplicate(&d.page_content))
+      {
+        continue;
+      }
+      self.index += 1;
+      self.options.check_chunk_count(self.index)?;
+      if !(self.on_chunk)(Chunk::new(self.index - 1, d)) {
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+}
+
 pub struct Doc {
   pub name: String,
   pub chunks: Vec<Chunk>,
+  pub metadata: DocMetadata,
 }
 
 impl Doc {
@@ -38,111 +433,556 @@ impl Doc {
     Self::with_options(file_path, doc, DocOptions::default())
   }
 
+  /// Returns the SHA3-256 of the contents of the chunks as hex, documents
+  /// with the same chunks have the same hash whatever their name.
+  pub fn hash(&self) -> String {
+    let mut hasher = Sha3_256::new();
+    for chunk in &self.chunks {
+      // the length keeps the boundaries of the chunks apart
+      hasher.update((chunk.content.len() as u64).to_le_bytes());
+      hasher.update(chunk.content.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+  }
+
   pub fn with_options(file_path: &str, doc: &[u8], options: DocOptions) -> LoaderResult<Self> {
-    if let Some(kind) =
-      infer::get(&doc[..4096.min(doc.len())]).or(infer::get_from_path(file_path).ok().flatten())
+    // embedded documents share the deadline of the outermost document
+    let options = DocOptions {
+      deadline: options.deadline.or(options.timeout.map(Deadline::new)),
+      ..options
+    };
+    Self::parse(file_path, doc, options)
+  }
+
+  /// Loads the document, checking the limits of the options on the documents
+  /// embedded in it as a whole.
+  fn parse(file_path: &str, doc: &[u8], options: DocOptions) -> LoaderResult<Self> {
+    let limits = options.clone();
+    let mut doc = Self::load(file_path, doc, options)?;
+    limits.check_deadline()?;
+    limits.check_chunk_count(doc.chunks.len())?;
+    limits.check_text_size(doc.chunks.iter().filter_map(|c| c.end).max().unwrap_or(0))?;
+
+    doc.metadata.language = doc
+      .metadata
+      .language
+      .as_deref()
+      .and_then(normalize_language);
+
+    // source code and data files have no natural language of their own
+    let path = PathBuf::from(file_path);
+    if doc.metadata.language.is_none()
+      && get_language_by_filename(file_path).is_err()
+      && DataFormat::from_extension(path.ext_str()).is_none()
     {
+      doc.metadata.language = detect_language(&doc.chunks);
+    }
+    Ok(doc)
+  }
+
+  fn load(file_path: &str, doc: &[u8], mut options: DocOptions) -> LoaderResult<Self> {
+    let head = &doc[..4096.min(doc.len())];
+    // the names of embedded files are no paths to read from
+    let from_path = || infer::get_from_path(file_path).ok().flatten();
+    let kind = infer::get(head).or_else(|| (!options.embedded).then(from_path).flatten());
+    let extension = PathBuf::from(file_path).ext_str().to_lowercase();
+    let registry = options.registry();
+    // formats are named by their detected type first, then by the extension
+    let formats = [
+      kind.map(|k| k.mime_type()),
+      kind.map(|k| k.extension()),
+      Some(extension.as_str()),
+    ];
+    let formats = formats.into_iter().flatten().collect::<Vec<_>>();
+    // set for every document, so embedded documents do not inherit it
+    options.format_splitter = registry.splitter(&formats);
+    if let Some(entry) = registry.find(kind.map(|k| k.mime_type()), &formats, head) {
+      return Self::from_fn(
+        file_path,
+        || entry.load(file_path, doc, &options),
+        entry.splitter(),
+        &options,
+      );
+    }
+
+    if let Some(kind) = kind {
       if kind.extension() == "pdf" {
-        return Self::load_pdf(file_path, doc);
+        return Self::load_pdf(file_path, doc, &options);
       } else if kind.extension() == "docx" {
-        return Self::load_docx(file_path, doc);
+        return Self::load_docx(file_path, doc, &options);
+      } else if kind.extension() == "epub" {
+        return Self::load_epub(file_path, doc, &options);
       } else if kind.extension() == "html" {
-        return Self::load_html(file_path, doc);
-      }
-    } else if let Ok(string) = String::from_utf8(doc.to_vec()).or_else(|_| {
-      String::from_utf16(
-        &doc
-          .chunks_exact(2)
-          .map(|b| u16::from_le_bytes([b[0], b[1]]))
-          .collect::<Vec<_>>(),
-      )
-    }) {
-      let path = PathBuf::from(file_path);
-      match path.ext_str() {
-        "md" => {
-          let loader = TextLoader::new(string);
-          let splitter = MarkdownSplitter::default();
-          return Self::from_loader(file_path, loader, splitter);
+        return Self::load_html(file_path, doc, &options);
+      } else if kind.extension() == "odt" {
+        return Self::load_odt(file_path, doc, &options);
+      } else if kind.extension() == "rtf" {
+        return Self::load_rtf(file_path, doc, &options);
+      } else if matches!(kind.extension(), "zip" | "tar" | "gz") {
+        return Self::load_archive(file_path, doc, kind.extension(), &options);
+      } else if matches!(kind.extension(), "pptx" | "odp") {
+        return Self::load_presentation(file_path, doc, &options);
+      } else if matches!(kind.extension(), "xlsx" | "xls" | "ods") {
+        return Self::load_spreadsheet(file_path, doc, &options);
+      }
+    } else if let Some((string, encoding)) = decode_text(doc) {
+      let mut doc = Self::load_text(file_path, doc, string, &options)?;
+      doc.metadata.encoding = Some(encoding.to_string());
+      return Ok(doc);
+    }
+    Err(LoaderError::Other("Failed to infer document type".into()))
+  }
+
+  /// Loads text files by their extension, `string` is the decoded `doc`.
+  fn load_text(
+    file_path: &str,
+    doc: &[u8],
+    string: String,
+    options: &DocOptions,
+  ) -> LoaderResult<Self> {
+    let path = PathBuf::from(file_path);
+    match path.ext_str() {
+      "md" => {
+        let loader = || Ok(TextLoader::new(string));
+        return Self::from_loader(file_path, loader, SplitterKind::Markdown, options);
+      }
+      "eml" => return Self::load_email(file_path, || EmailLoader::new(doc), options),
+      "mbox" => return Self::load_email(file_path, || EmailLoader::from_mbox(doc), options),
+      "ipynb" => {
+        let loader =
+          || Ok(NotebookLoader::new(&string)?.with_parser_threshold(options.code_threshold));
+        return Self::from_loader(file_path, loader, SplitterKind::Markdown, options);
+      }
+      "csv" | "tsv" => {
+        let delimiter = if path.ext_str() == "tsv" { b'\t' } else { b',' };
+        let loader = || SpreadsheetLoader::from_csv(string.as_bytes(), delimiter);
+        return Self::from_loader(file_path, loader, SplitterKind::Table, options);
+      }
+      "json" | "yaml" | "yml" | "toml" => {
+        let format = DataFormat::from_extension(path.ext_str());
+        // files that do not parse, such as JSON with comments, are loaded
+        // as plain text
+        let loader = format.and_then(|f| catch_panic(|| StructuredLoader::new(&string, f)).ok());
+        if let Some(loader) = loader {
+          return Self::from_loader(file_path, || Ok(loader), SplitterKind::Structured, options);
         }
-        "rs" | "c" | "cpp" | "h" | "hpp" | "js" | "ts" | "tsx" | "go" | "py" => {
-          let name = path.full_str().to_string();
-          let loader =
+      }
+      "srt" | "vtt" => {
+        // files without cues are loaded as plain text
+        if let Ok(loader) = catch_panic(|| SubtitleLoader::new(&string)) {
+          return Self::from_loader(file_path, || Ok(loader), SplitterKind::Transcript, options);
+        }
+      }
+      "rs" | "c" | "cpp" | "h" | "hpp" | "js" | "ts" | "tsx" | "go" | "py" | "java" | "kt"
+      | "kts" | "scala" | "sc" | "cs" => {
+        let name = path.full_str().to_string();
+        let language = get_language_by_filename(&name)?;
+        let loader = || {
+          Ok(
             SourceCodeLoader::from_string(string).with_parser_option(LanguageParserOptions {
-              language: get_language_by_filename(&name)?,
+              language,
               parser_threshold: options.code_threshold,
-            });
-          let splitter = TokenSplitter::default();
-          return Self::from_loader(file_path, loader, splitter);
-        }
-        _ => {}
+              ..Default::default()
+            }),
+          )
+        };
+        return Self::from_loader(file_path, loader, SplitterKind::Token, options);
       }
-      let loader = TextLoader::new(string);
-      let splitter = TokenSplitter::default();
-      return Self::from_loader(file_path, loader, splitter);
+      _ => {}
     }
-    Err(LoaderError::Other("Failed to infer document type".into()))
+    let loader = || Ok(TextLoader::new(string));
+    Self::from_loader(file_path, loader, SplitterKind::Token, options)
+  }
+
+  /// Loads and splits the document with the loader built by `new_loader`,
+  /// which may split some of its documents differently than `splitter` does.
+  /// Loaders parse in their constructors as well, so panics of both turn
+  /// into errors.
+  fn from_loader<L: Loader + 'static>(
+    file_path: &str,
+    new_loader: impl FnOnce() -> LoaderResult<L>,
+    splitter: SplitterKind,
+    options: &DocOptions,
+  ) -> Result<Doc, LoaderError> {
+    let splitter = options.splitter(splitter);
+    Self::from_split(
+      file_path,
+      || new_loader()?.load_and_split(splitter),
+      options,
+    )
+  }
+
+  fn from_fn(
+    file_path: &str,
+    load: impl FnOnce() -> LoaderResult<Vec<Document>>,
+    splitter: SplitterKind,
+    options: &DocOptions,
+  ) -> Result<Doc, LoaderError> {
+    let split = || {
+      let docs = load()?;
+      options.check_deadline()?;
+      options.check_text_size(docs.iter().map(|d| d.page_content.len()).sum())?;
+      Ok(options.splitter(splitter).split_documents(&docs)?)
+    };
+    Self::from_split(file_path, split, options)
   }
 
-  fn from_loader(
+  /// Turns the documents returned by `split` into the chunks of the document.
+  fn from_split(
     file_path: &str,
-    loader: impl Loader + 'static,
-    splitter: impl TextSplitter + 'static,
+    split: impl FnOnce() -> LoaderResult<Vec<Document>>,
+    options: &DocOptions,
   ) -> Result<Doc, LoaderError> {
     let name = file_path.to_string();
-    let chunks = catch_unwind(AssertUnwindSafe(|| {
-      Self::get_chunks_from_loader(loader, splitter)
-    }))
-    .map_err(|e| {
-      LoaderError::Other(match e.downcast::<String>() {
-        Ok(v) => *v,
-        Err(e) => match e.downcast::<&str>() {
-          Ok(v) => v.to_string(),
-          _ => "Unknown Source of Error".to_owned(),
-        },
+    let chunks = catch_panic(|| {
+      let mut docs = split()?;
+      options.check_deadline()?;
+      if let Some(mut dedup) = options.deduplicator() {
+        docs.retain(|d| !dedup.is_duplicate(&d.page_content));
+      }
+      options.check_chunk_count(docs.len())?;
+      Ok(
+        docs
+          .into_iter()
+          .enumerate()
+          .map(|(index, d)| Chunk::new(index, d))
+          .collect(),
+      )
+    })?;
+
+    Ok(Self {
+      name,
+      chunks,
+      metadata: DocMetadata::default(),
+    })
+  }
+
+  /// Parses the file at `file_path` like [`Doc::with_options`], handing the
+  /// chunks to `on_chunk` one after another instead of collecting them.
+  /// Parsing stops once `on_chunk` returns `false`.
+  ///
+  /// Plain text and markdown files are read and split window by window and
+  /// PDF files page by page, so memory stays bounded by the window or the
+  /// page. Other formats are loaded as a whole before their chunks are handed
+  /// out.
+  pub fn stream(
+    file_path: &str,
+    options: DocOptions,
+    mut on_chunk: impl FnMut(Chunk) -> bool,
+  ) -> LoaderResult<()> {
+    let mut file = File::open(file_path)?;
+    let mut head = vec![];
+    (&mut file).take(4096).read_to_end(&mut head)?;
+    file.rewind()?;
+
+    let registry = options.registry();
+    let extension = PathBuf::from(file_path).ext_str().to_lowercase();
+    if let Some(kind) = infer::get(&head).filter(|kind| kind.extension() == "pdf") {
+      let formats = [kind.mime_type(), kind.extension(), extension.as_str()];
+      if registry
+        .find(Some(kind.mime_type()), &formats, &head)
+        .is_none()
+      {
+        let options = DocOptions {
+          deadline: options.timeout.map(Deadline::new),
+          format_splitter: registry.splitter(&formats),
+          ..options
+        };
+        return Self::stream_pdf(file, &options, on_chunk);
+      }
+    }
+
+    let Some(splitter) = Self::stream_splitter(file_path, &head, &registry) else {
+      let mut doc = vec![];
+      file.read_to_end(&mut doc)?;
+      for chunk in Self::with_options(file_path, &doc, options)?.chunks {
+        if !on_chunk(chunk) {
+          break;
+        }
+      }
+      return Ok(());
+    };
+
+    let options = DocOptions {
+      deadline: options.timeout.map(Deadline::new),
+      format_splitter: registry.splitter(&[&extension]),
+      ..options
+    };
+    let window_size = options.stream_window_size.max(4);
+    let kind = options.splitter_kind(splitter);
+    let mut stream = ChunkStream::new(&options, splitter, on_chunk);
+    let mut reader = BufReader::new(file);
+    let mut window = Vec::with_capacity(window_size);
+    let (mut start, mut line) = (0, 1);
+    // the headings enclosing the next window
+    let mut headings: Vec<(usize, String)> = vec![];
+    catch_panic(|| loop {
+      options.check_deadline()?;
+      (&mut reader)
+        .take((window_size - window.len()) as u64)
+        .read_to_end(&mut window)?;
+      let eof = window.len() < window_size;
+      // windows end after a line break, or between two characters of lines
+      // longer than the window
+      let cut = if eof {
+        window.len()
+      } else {
+        match window.iter().rposition(|b| *b == b'\n') {
+          Some(i) => i + 1,
+          None => match std::str::from_utf8(&window) {
+            Err(e) if e.valid_up_to() > 0 => e.valid_up_to(),
+            _ => window.len(),
+          },
+        }
+      };
+      let text = String::from_utf8(window.drain(..cut).collect())?;
+
+      let mut metadata = HashMap::from([("start".to_string(), Value::from(start))]);
+      if kind == SplitterKind::Markdown {
+        metadata.insert("line_start".to_string(), Value::from(line));
+        metadata.insert(
+          "heading_stack".to_string(),
+          serde_json::to_value(&headings)?,
+        );
+        update_heading_stack(&mut headings, &text);
+      }
+      start += text.len();
+      line += text.matches('\n').count();
+      options.check_text_size(start)?;
+      if !stream.push(Document::new(text).with_metadata(metadata))? || eof {
+        return Ok(());
+      }
+    })
+  }
+
+  /// Streams the chunks of a PDF file page by page.
+  fn stream_pdf(
+    file: File,
+    options: &DocOptions,
+    on_chunk: impl FnMut(Chunk) -> bool,
+  ) -> LoaderResult<()> {
+    let mut stream = ChunkStream::new(options, SplitterKind::Token, on_chunk);
+    let mut text_size = 0;
+    catch_panic(|| {
+      Self::pdf_loader(BufReader::new(file), options)?.stream_pages(|page| {
+        text_size += page.page_content.len();
+        options.check_text_size(text_size)?;
+        stream.push(page)
       })
-    })??;
-
-    Ok(Self { name, chunks })
-  }
-
-  fn get_chunks_from_loader(
-    loader: impl Loader + 'static,
-    splitter: impl TextSplitter + 'static,
-  ) -> Result<Vec<Chunk>, LoaderError> {
-    let docs = loader.load_and_split(splitter)?;
-    Ok(
-      docs
-        .into_iter()
-        .enumerate()
-        .map(|(index, d)| Chunk {
-          index,
-          content: d.page_content,
-          ..Chunk::default()
-        })
-        .collect(),
-    )
+    })
+  }
+
+  /// Returns the splitter of files that are split window by window by
+  /// [`Doc::stream`], that is of plain text and markdown files that are not
+  /// taken over by a registered loader.
+  fn stream_splitter(
+    file_path: &str,
+    head: &[u8],
+    registry: &LoaderRegistry,
+  ) -> Option<SplitterKind> {
+    if infer::get(head).is_some() {
+      return None;
+    }
+    let extension = PathBuf::from(file_path).ext_str().to_lowercase();
+    if registry.find(None, &[&extension], head).is_some() {
+      return None;
+    }
+    // text with a byte order mark or in a legacy encoding is decoded as a
+    // whole, the head may end inside of a character though
+    if head.starts_with(&[0xef, 0xbb, 0xbf])
+      || std::str::from_utf8(head).is_err_and(|e| e.error_len().is_some())
+    {
+      return None;
+    }
+    match PathBuf::from(file_path).ext_str() {
+      "md" => Some(SplitterKind::Markdown),
+      "eml" | "mbox" | "ipynb" | "csv" | "tsv" | "json" | "yaml" | "yml" | "toml" | "srt"
+      | "vtt" => None,
+      _ if get_language_by_filename(file_path).is_ok() => None,
+      _ => Some(SplitterKind::Token),
+    }
   }
 
-  fn load_docx(file_path: &str, doc: &[u8]) -> LoaderResult<Self> {
-    let loader = DocxLoader::new(Cursor::new(doc))
-      .ok_or(LoaderError::Other("Failed to parse docx document".into()))?;
-    let splitter = TokenSplitter::default();
-    Self::from_loader(file_path, loader, splitter)
+  fn load_docx(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let loader = || {
+      Ok(
+        DocxLoader::new(Cursor::new(doc))
+          .ok_or(LoaderError::Other("Failed to parse docx document".into()))?
+          .with_split_sections(true)
+          .with_table_options(options.splitter_options.clone()),
+      )
+    };
+    let metadata = DocxLoader::read_metadata(Cursor::new(doc))?;
+    let doc = Self::from_loader(file_path, loader, SplitterKind::Markdown, options)?;
+    Ok(Self { metadata, ..doc })
   }
 
-  fn load_html(file_path: &str, doc: &[u8]) -> LoaderResult<Self> {
-    let loader = HtmlLoader::from_string(
-      String::from_utf8(doc.to_vec())?,
-      Url::parse(file_path).or(Url::parse("https://example.com/"))?,
-    );
-    let splitter = TokenSplitter::default();
-    Self::from_loader(file_path, loader, splitter)
+  /// Appends the chunks of a document embedded in this one, tagged with its
+  /// `name` under `key`. The embedded text is laid out after `offset`, the
+  /// offset after it is returned.
+  fn append_embedded(&mut self, embedded: Doc, key: &str, name: &str, offset: usize) -> usize {
+    let len = embedded
+      .chunks
+      .iter()
+      .filter_map(|c| c.end)
+      .max()
+      .unwrap_or(0);
+    for mut chunk in embedded.chunks {
+      chunk.index = self.chunks.len();
+      chunk.start = chunk.start.map(|start| start + offset);
+      chunk.end = chunk.end.map(|end| end + offset);
+      // files embedded in embedded files keep their path
+      let path = match chunk.metadata.get(key).and_then(Value::as_str) {
+        Some(inner) => format!("{}/{}", name, inner),
+        None => name.to_string(),
+      };
+      chunk.metadata.insert(key.to_string(), Value::from(path));
+      self.chunks.push(chunk);
+    }
+    offset + len
+  }
+
+  /// Loads the messages followed by their attachments, which are loaded as
+  /// documents of their own. Attachments that cannot be loaded, like images,
+  /// are skipped, unless they exceed a limit of archives or of the parse.
+  /// Attachments count against the limits of archives like their files.
+  fn load_email(
+    file_path: &str,
+    new_loader: impl FnOnce() -> LoaderResult<EmailLoader>,
+    options: &DocOptions,
+  ) -> LoaderResult<Self> {
+    let mut attachments = vec![];
+    let loader = || {
+      let mut loader = new_loader()?;
+      attachments = loader.take_attachments();
+      Ok(loader)
+    };
+    let mut doc = Self::from_loader(file_path, loader, SplitterKind::Token, options)?;
+    if attachments.is_empty() {
+      return Ok(doc);
+    }
+
+    let (budget, options) = options.nested()?;
+    // attachments are laid out after the messages in the extracted text
+    let mut offset = doc.chunks.iter().filter_map(|c| c.end).max().unwrap_or(0);
+    for attachment in attachments {
+      options.check_deadline()?;
+      budget.charge(attachment.content.len() as u64)?;
+      let embedded =
+        match Self::with_options(&attachment.name, &attachment.content, options.clone()) {
+          Ok(embedded) => embedded,
+          Err(e) if e.is_archive_limit() || e.is_parse_limit() => return Err(e),
+          Err(_) => continue,
+        };
+      let first = doc.chunks.len();
+      offset = doc.append_embedded(embedded, "attachment", &attachment.name, offset);
+      for chunk in &mut doc.chunks[first..] {
+        chunk.metadata.insert(
+          "message_index".to_string(),
+          Value::from(attachment.message_index),
+        );
+      }
+    }
+    Ok(doc)
+  }
+
+  /// Loads the files of a ZIP, tar or gzip archive one after another. Files
+  /// that cannot be loaded are skipped, exceeding a limit of the archive or
+  /// of the parse fails the whole archive.
+  fn load_archive(
+    file_path: &str,
+    doc: &[u8],
+    extension: &str,
+    options: &DocOptions,
+  ) -> LoaderResult<Self> {
+    // nested archives draw from the budget of the outermost one
+    let (budget, options) = options.nested()?;
+    let archive = match extension {
+      "zip" => Archive::from_zip(Cursor::new(doc), &budget)?,
+      "tar" => Archive::from_tar(doc, &budget)?,
+      _ => Archive::from_gzip(doc, file_path, &budget)?,
+    };
+
+    let mut doc = Self {
+      name: file_path.to_string(),
+      chunks: vec![],
+      metadata: DocMetadata::default(),
+    };
+    let mut offset = 0;
+    for entry in archive.entries {
+      options.check_deadline()?;
+      match Self::with_options(&entry.path, &entry.content, options.clone()) {
+        Ok(embedded) => offset = doc.append_embedded(embedded, "entry_path", &entry.path, offset),
+        Err(e) if e.is_archive_limit() || e.is_parse_limit() => return Err(e),
+        Err(_) => {}
+      }
+    }
+    Ok(doc)
   }
 
-  fn load_pdf(file_path: &str, doc: &[u8]) -> LoaderResult<Self> {
-    let loader = PdfExtractLoader::new(Cursor::new(doc))?;
-    let splitter = TokenSplitter::default();
-    Self::from_loader(file_path, loader, splitter)
+  fn load_epub(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let loader = || EpubLoader::new(Cursor::new(doc));
+    Self::from_loader(file_path, loader, SplitterKind::Token, options)
+  }
+
+  fn load_html(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let html = String::from_utf8(doc.to_vec())?;
+    let metadata = html_metadata(&html);
+    let url = Url::parse(file_path).or(Url::parse("https://example.com/"))?;
+    let loader = || Ok(HtmlLoader::from_string(html, url));
+    let doc = Self::from_loader(file_path, loader, SplitterKind::Token, options)?;
+    Ok(Self { metadata, ..doc })
+  }
+
+  fn load_odt(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let loader = || OdtLoader::new(Cursor::new(doc));
+    Self::from_loader(file_path, loader, SplitterKind::Markdown, options)
+  }
+
+  fn load_rtf(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let loader = || RtfLoader::new(doc);
+    Self::from_loader(file_path, loader, SplitterKind::Markdown, options)
+  }
+
+  fn load_presentation(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let loader = || PresentationLoader::new(Cursor::new(doc));
+    Self::from_loader(file_path, loader, SplitterKind::Markdown, options)
+  }
+
+  fn load_spreadsheet(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    Self::from_loader(
+      file_path,
+      || SpreadsheetLoader::new(Cursor::new(doc)),
+      SplitterKind::Table,
+      options,
+    )
+  }
+
+  /// Returns the loader of a PDF file, decrypted with the password of the
+  /// options.
+  fn pdf_loader<R: Read>(reader: R, options: &DocOptions) -> LoaderResult<PdfExtractLoader> {
+    let mut loader = PdfExtractLoader::new(reader)?.with_strip_headers(options.strip_pdf_headers);
+    if let Some(password) = &options.password {
+      loader = loader.with_password(password)?;
+    }
+    if let Some(deadline) = options.deadline {
+      loader = loader.with_deadline(deadline);
+    }
+    if let Some(abort) = &options.abort {
+      loader = loader.with_abort(abort.clone());
+    }
+    Ok(loader)
+  }
+
+  fn load_pdf(file_path: &str, doc: &[u8], options: &DocOptions) -> LoaderResult<Self> {
+    let mut metadata = DocMetadata::default();
+    let loader = || {
+      let loader = Self::pdf_loader(Cursor::new(doc), options)?.with_split_pages(true);
+      metadata = loader.metadata()?;
+      Ok(loader)
+    };
+    let doc = Self::from_loader(file_path, loader, SplitterKind::Token, options)?;
+    Ok(Self { metadata, ..doc })
   }
 }
 
@@ -150,18 +990,29 @@ impl Doc {
 mod tests {
   use std::{
     fs::{read, read_to_string},
+    io::Write,
     path::PathBuf,
   };
 
   use super::*;
 
-  const FIXTURES: [&str; 6] = [
+  const FIXTURES: [&str; 16] = [
     "demo.docx",
     "sample.pdf",
     "sample.html",
     "sample.rs",
     "sample.c",
     "sample.ts",
+    "sample.java",
+    "sample.kt",
+    "sample.scala",
+    "sample.cs",
+    "sample.xlsx",
+    "sample.csv",
+    "sample.pptx",
+    "sample.odp",
+    "sample.rtf",
+    "sample.odt",
   ];
 
   fn get_fixtures() -> PathBuf {
@@ -173,7 +1024,12 @@ mod tests {
     let fixtures = get_fixtures();
     for fixture in FIXTURES.iter() {
       let buffer = read(fixtures.join(fixture)).unwrap();
-      let doc = Doc::with_options(fixture, &buffer, DocOptions { code_threshold: 0 }).unwrap();
+      let doc = Doc::with_options(
+        fixture,
+        &buffer,
+        DocOptions::default().with_code_threshold(0),
+      )
+      .unwrap();
       for chunk in doc.chunks.iter() {
         let output =
           read_to_string(fixtures.join(format!("{}.{}.md", fixture, chunk.index))).unwrap();
@@ -181,4 +1037,498 @@ mod tests {
       }
     }
   }
+
+  #[test]
+  fn test_chunk_offsets() {
+    let fixtures = get_fixtures();
+    for fixture in [
+      "sample.rs",
+      "sample.c",
+      "sample.ts",
+      "sample.java",
+      "sample.kt",
+      "sample.scala",
+      "sample.cs",
+    ] {
+      let source = read_to_string(fixtures.join(fixture)).unwrap();
+      let doc = Doc::with_options(
+        fixture,
+        source.as_bytes(),
+        DocOptions::default().with_code_threshold(0),
+      )
+      .unwrap();
+      for chunk in doc.chunks.iter() {
+        let (start, end) = (chunk.start.unwrap(), chunk.end.unwrap());
+        assert_eq!(&source[start..end], chunk.content);
+      }
+    }
+  }
+
+  #[test]
+  fn test_metadata() {
+    let fixtures = get_fixtures();
+    let html = read(fixtures.join("sample.html")).unwrap();
+    let doc = Doc::new("sample.html", &html).unwrap();
+    assert_eq!(doc.metadata.title.as_deref(), Some("Example Domain"));
+    assert_eq!(doc.metadata.language.as_deref(), Some("en"));
+
+    let text = "Der schnelle braune Fuchs springt über den faulen Hund. Danach \
+                schläft er den ganzen Nachmittag im warmen Gras.";
+    let doc = Doc::new("notes.txt", text.as_bytes()).unwrap();
+    assert_eq!(doc.metadata.language.as_deref(), Some("de"));
+
+    let doc = Doc::new("sample.rs", &read(fixtures.join("sample.rs")).unwrap()).unwrap();
+    assert_eq!(doc.metadata.language, None);
+  }
+
+  #[test]
+  fn test_splitter_options() {
+    let source = read_to_string(get_fixtures().join("sample.rs")).unwrap();
+    let default = Doc::new("sample.rs", source.as_bytes()).unwrap();
+    let options = DocOptions::default()
+      .with_chunk_size(32)
+      .with_chunk_overlap(0)
+      .with_encoding_name("o200k_base");
+    let doc = Doc::with_options("sample.rs", source.as_bytes(), options.clone()).unwrap();
+    assert!(doc.chunks.len() > default.chunks.len());
+    let bpe = tiktoken_rs::o200k_base().unwrap();
+    assert!(doc
+      .chunks
+      .iter()
+      .all(|chunk| bpe.encode_ordinary(&chunk.content).len() <= 32));
+
+    // the splitter of the format is replaced, the markdown splitter records
+    // the lines of every chunk
+    let doc = Doc::with_options(
+      "sample.rs",
+      source.as_bytes(),
+      options.with_splitter(SplitterKind::Markdown),
+    )
+    .unwrap();
+    assert!(doc
+      .chunks
+      .iter()
+      .all(|chunk| chunk.metadata.contains_key("line_start")));
+  }
+
+  #[test]
+  fn test_loader_splitting() {
+    let fixtures = get_fixtures();
+    let options = DocOptions::default()
+      .with_chunk_size(64)
+      .with_chunk_overlap(0);
+
+    // tables larger than a chunk are split by rows, repeating their header
+    let docx = read(fixtures.join("demo.docx")).unwrap();
+    let doc = Doc::with_options("demo.docx", &docx, options.clone()).unwrap();
+    let calendar = doc
+      .chunks
+      .iter()
+      .filter(|c| c.content.starts_with("|               |  |     |  |     |"))
+      .collect::<Vec<_>>();
+    assert!(calendar.len() > 1);
+    assert!(calendar
+      .iter()
+      .all(|c| c.metadata.contains_key("row_start")));
+
+    // code cells keep the chunks of the language parser
+    let notebook = read(fixtures.join("sample.ipynb")).unwrap();
+    let doc = Doc::with_options("sample.ipynb", &notebook, options.with_code_threshold(0)).unwrap();
+    let code = doc
+      .chunks
+      .iter()
+      .filter(|c| c.metadata.get("cell_type") == Some(&Value::from("code")))
+      .collect::<Vec<_>>();
+    assert!(code
+      .iter()
+      .any(|c| c.metadata.get("symbol") == Some(&Value::from("mean"))));
+    assert!(code.iter().all(|c| !c.metadata.contains_key("line_start")));
+    assert!(code.iter().all(|c| c.end.is_some()));
+  }
+
+  #[test]
+  fn test_sentence_splitter() {
+    let text = "The first sentence is here. The second one follows it. A third one \
+                closes the paragraph.";
+    let options = DocOptions::default()
+      .with_chunk_size(12)
+      .with_chunk_overlap(0);
+    // plain text is split along words unless sentences are asked for
+    let doc = Doc::with_options("notes.txt", text.as_bytes(), options.clone()).unwrap();
+    assert!(!doc.chunks.iter().all(|chunk| chunk.content.ends_with('.')));
+
+    let options = options.with_splitter(SplitterKind::Sentence);
+    let doc = Doc::with_options("notes.txt", text.as_bytes(), options).unwrap();
+    assert!(doc.chunks.len() > 1);
+    assert!(doc.chunks.iter().all(|chunk| chunk.content.ends_with('.')));
+  }
+
+  #[test]
+  fn test_encoding() {
+    let text = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。";
+    let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+    let doc = Doc::new("novel.txt", &shift_jis).unwrap();
+    assert_eq!(doc.chunks[0].content, text);
+    assert_eq!(doc.metadata.encoding.as_deref(), Some("Shift_JIS"));
+
+    let doc = Doc::new("notes.md", b"# Notes\n\nPlain ASCII").unwrap();
+    assert_eq!(doc.metadata.encoding.as_deref(), Some("UTF-8"));
+
+    // binary files are not loaded as text
+    assert!(Doc::new("blob.bin", &[0x00, 0x01, 0x02, 0x03, 0xff, 0x10]).is_err());
+  }
+
+  #[test]
+  fn test_subtitles() {
+    let fixtures = get_fixtures();
+    for fixture in ["sample.srt", "sample.vtt"] {
+      let buffer = read(fixtures.join(fixture)).unwrap();
+      let doc = Doc::with_options(
+        fixture,
+        &buffer,
+        DocOptions::default()
+          .with_chunk_size(24)
+          .with_chunk_overlap(0),
+      )
+      .unwrap();
+      assert!(doc.chunks.len() > 1);
+      let mut last_end_ms = 0;
+      for chunk in &doc.chunks {
+        let start_ms = chunk.metadata["start_ms"].as_u64().unwrap();
+        let end_ms = chunk.metadata["end_ms"].as_u64().unwrap();
+        assert!(last_end_ms <= start_ms && start_ms < end_ms);
+        last_end_ms = end_ms;
+      }
+      assert_eq!(doc.chunks[0].metadata["start_ms"], 1000);
+    }
+  }
+
+  #[test]
+  fn test_dedup() {
+    let text = ["apple", "cherry", "pear", "plum", "grape", "lemon"]
+      .iter()
+      .enumerate()
+      .map(|(page, fruit)| {
+        format!(
+          "ACME Corp. Confidential. Page {}.\n\nThe {} harvest of this season exceeded every \
+           forecast.",
+          page + 1,
+          fruit
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n\n");
+    let options = DocOptions::default()
+      .with_chunk_size(16)
+      .with_chunk_overlap(0);
+    let doc = Doc::with_options("report.txt", text.as_bytes(), options.clone()).unwrap();
+    let deduped = Doc::with_options(
+      "report.txt",
+      text.as_bytes(),
+      options.clone().with_dedup_threshold(0.9).unwrap(),
+    )
+    .unwrap();
+    // the headers of the pages after the first one are dropped
+    assert_eq!(deduped.chunks.len(), doc.chunks.len() - 5);
+    let headers = deduped
+      .chunks
+      .iter()
+      .filter(|chunk| chunk.content.contains("Confidential"))
+      .count();
+    assert_eq!(headers, 1);
+    assert!(deduped
+      .chunks
+      .iter()
+      .enumerate()
+      .all(|(index, chunk)| chunk.index == index));
+
+    // hashes only depend on the content
+    let copy = Doc::with_options("copy.txt", text.as_bytes(), DocOptions::default()).unwrap();
+    assert_eq!(
+      Doc::new("report.txt", text.as_bytes()).unwrap().hash(),
+      copy.hash()
+    );
+    assert_ne!(doc.hash(), deduped.hash());
+    assert_eq!(doc.chunks[1].hash(), deduped.chunks[1].hash());
+    assert_eq!(doc.chunks[0].hash().len(), 64);
+
+    for threshold in [0.0, -0.5, 1.5, f64::NAN] {
+      assert!(matches!(
+        options.clone().with_dedup_threshold(threshold),
+        Err(LoaderError::InvalidDedupThreshold(_))
+      ));
+    }
+  }
+
+  #[test]
+  fn test_registry() {
+    let mut registry = LoaderRegistry::new();
+    registry.register(
+      LoaderEntry::new("log", |_, doc, _| {
+        let text = String::from_utf8_lossy(doc).to_uppercase();
+        Ok(vec![Document::new(text).with_metadata(HashMap::from([(
+          "format".to_string(),
+          Value::from("log"),
+        )]))])
+      })
+      .with_extension("log"),
+    );
+    registry.set_splitter("rs", SplitterKind::Markdown);
+    let options = DocOptions::default().with_registry(Arc::new(registry));
+
+    let doc = Doc::with_options("server.log", b"started\nstopped", options.clone()).unwrap();
+    assert_eq!(doc.chunks.len(), 1);
+    assert_eq!(doc.chunks[0].content, "STARTED\nSTOPPED");
+    assert_eq!(doc.chunks[0].metadata["format"], "log");
+
+    let source = read(get_fixtures().join("sample.rs")).unwrap();
+    let doc = Doc::with_options("sample.rs", &source, options).unwrap();
+    assert!(doc
+      .chunks
+      .iter()
+      .all(|chunk| chunk.metadata.contains_key("line_start")));
+  }
+
+  #[test]
+  fn test_parse_limits() {
+    let source = read_to_string(get_fixtures().join("sample.rs")).unwrap();
+    let parse = |options: DocOptions| Doc::with_options("sample.rs", source.as_bytes(), options);
+
+    let options = DocOptions::default()
+      .with_chunk_size(8)
+      .with_chunk_overlap(0)
+      .with_max_chunks(2);
+    assert!(matches!(parse(options), Err(LoaderError::TooManyChunks(2))));
+    let options = DocOptions::default().with_max_text_size(16);
+    assert!(matches!(parse(options), Err(LoaderError::TextTooLarge(16))));
+    let options = DocOptions::default().with_timeout(Duration::ZERO);
+    assert!(matches!(parse(options), Err(LoaderError::Timeout(_))));
+
+    let options = DocOptions::default()
+      .with_timeout(Duration::from_secs(60))
+      .with_max_text_size(source.len())
+      .with_max_chunks(100);
+    assert!(parse(options).is_ok());
+
+    // the deadline is checked before every page of PDF files
+    let buffer = read(get_fixtures().join("report.pdf")).unwrap();
+    let options = DocOptions::default().with_timeout(Duration::ZERO);
+    assert!(matches!(
+      Doc::with_options("report.pdf", &buffer, options),
+      Err(LoaderError::Timeout(_))
+    ));
+
+    // limits hit by the files of an archive fail the whole archive
+    let buffer = read(get_fixtures().join("sample.zip")).unwrap();
+    let options = DocOptions::default().with_max_text_size(16);
+    assert!(matches!(
+      Doc::with_options("sample.zip", &buffer, options),
+      Err(LoaderError::TextTooLarge(16))
+    ));
+  }
+
+  #[test]
+  fn test_stream() {
+    let text = (0..2000)
+      .map(|i| format!("{} request handled in {}ms\n", i, i % 97))
+      .collect::<String>();
+    let path = std::env::temp_dir().join("affine_doc_loader_stream.log");
+    std::fs::write(&path, &text).unwrap();
+    let path = path.to_str().unwrap();
+
+    let options = DocOptions::default()
+      .with_chunk_size(64)
+      .with_chunk_overlap(0)
+      .with_stream_window_size(4096);
+    let mut chunks = vec![];
+    Doc::stream(path, options.clone(), |chunk| {
+      chunks.push(chunk);
+      true
+    })
+    .unwrap();
+    assert!(chunks.len() > 1);
+    for (i, chunk) in chunks.iter().enumerate() {
+      assert_eq!(chunk.index, i);
+      assert_eq!(
+        &text[chunk.start.unwrap()..chunk.end.unwrap()],
+        chunk.content
+      );
+    }
+    // chunks of the windows cover the whole file
+    assert_eq!(chunks.last().unwrap().end, Some(text.trim_end().len()));
+
+    let mut count = 0;
+    Doc::stream(path, options, |_| {
+      count += 1;
+      count < 3
+    })
+    .unwrap();
+    assert_eq!(count, 3);
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn test_stream_markdown_headings() {
+    let text = format!(
+      "# Guide\n\n## Setup\n\n{}",
+      "Install the package and run it.\n\n".repeat(200)
+    );
+    let path = std::env::temp_dir().join("affine_doc_loader_stream.md");
+    std::fs::write(&path, &text).unwrap();
+
+    let options = DocOptions::default()
+      .with_chunk_size(64)
+      .with_chunk_overlap(0)
+      .with_stream_window_size(1024);
+    let mut chunks = vec![];
+    Doc::stream(path.to_str().unwrap(), options, |chunk| {
+      chunks.push(chunk);
+      true
+    })
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // the headings of the first window enclose the chunks of the later ones
+    let last = chunks.last().unwrap();
+    assert!(last.start.unwrap() > 1024);
+    assert_eq!(
+      last.metadata["heading_path"],
+      Value::from(vec!["Guide", "Setup"])
+    );
+    assert!(!last.metadata.contains_key("heading_stack"));
+  }
+
+  #[test]
+  fn test_stream_pdf() {
+    let path = get_fixtures().join("report.pdf");
+    let options = DocOptions::default()
+      .with_chunk_size(16)
+      .with_chunk_overlap(0);
+    let doc = Doc::with_options("report.pdf", &read(&path).unwrap(), options.clone()).unwrap();
+    let mut chunks = vec![];
+    Doc::stream(path.to_str().unwrap(), options.clone(), |chunk| {
+      chunks.push(chunk);
+      true
+    })
+    .unwrap();
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.len(), doc.chunks.len());
+    for (streamed, chunk) in chunks.iter().zip(&doc.chunks) {
+      assert_eq!(streamed.content, chunk.content);
+      assert_eq!(streamed.start, chunk.start);
+      assert_eq!(
+        streamed.metadata["page_number"],
+        chunk.metadata["page_number"]
+      );
+    }
+
+    // an abort stops parsing before the next page
+    let abort = Arc::new(AtomicBool::new(false));
+    let mut count = 0;
+    let result = Doc::stream(
+      path.to_str().unwrap(),
+      options.with_abort(abort.clone()),
+      |_| {
+        count += 1;
+        abort.store(true, Ordering::Relaxed);
+        true
+      },
+    );
+    assert!(matches!(result, Err(LoaderError::Aborted)));
+    assert!(count < chunks.len());
+  }
+
+  #[test]
+  fn test_email_attachments() {
+    let buffer = read(get_fixtures().join("sample.eml")).unwrap();
+    let doc = Doc::new("sample.eml", &buffer).unwrap();
+
+    let (messages, attachments): (Vec<_>, Vec<_>) = doc
+      .chunks
+      .iter()
+      .partition(|chunk| !chunk.metadata.contains_key("attachment"));
+    assert!(!messages.is_empty());
+    assert!(messages
+      .iter()
+      .all(|chunk| chunk.metadata["subject"] == "Launch plan"));
+    assert!(!attachments.is_empty());
+    assert!(attachments
+      .iter()
+      .all(|chunk| chunk.metadata["attachment"] == "notes.md"));
+    assert!(attachments[0].content.contains("Launch notes"));
+    // attachments are laid out after the messages
+    let end = messages.iter().filter_map(|c| c.end).max().unwrap();
+    assert!(attachments.iter().all(|c| c.start.unwrap() >= end));
+    assert!(doc.chunks.iter().enumerate().all(|(i, c)| c.index == i));
+
+    // attachments count against the limits of archives
+    let options = DocOptions::default().with_max_archive_depth(0);
+    assert!(matches!(
+      Doc::with_options("sample.eml", &buffer, options),
+      Err(LoaderError::ArchiveTooDeep(0))
+    ));
+    let options = DocOptions::default().with_max_archive_size(16);
+    assert!(matches!(
+      Doc::with_options("sample.eml", &buffer, options),
+      Err(LoaderError::ArchiveTooLarge(16))
+    ));
+  }
+
+  #[test]
+  fn test_archive() {
+    let buffer = read(get_fixtures().join("sample.zip")).unwrap();
+    let doc = Doc::new("sample.zip", &buffer).unwrap();
+
+    let mut paths = doc
+      .chunks
+      .iter()
+      .map(|chunk| chunk.metadata["entry_path"].as_str().unwrap())
+      .collect::<Vec<_>>();
+    paths.dedup();
+    assert_eq!(
+      paths,
+      [
+        "docs/guide.md",
+        "src/sample.rs",
+        "notes.tar.gz/notes/todo.txt"
+      ]
+    );
+    let source = read_to_string(get_fixtures().join("sample.rs")).unwrap();
+    let code = doc
+      .chunks
+      .iter()
+      .find(|chunk| chunk.metadata["entry_path"] == "src/sample.rs")
+      .unwrap();
+    assert!(source.contains(&code.content));
+
+    let options = DocOptions::default().with_max_archive_depth(1);
+    assert!(matches!(
+      Doc::with_options("sample.zip", &buffer, options),
+      Err(LoaderError::ArchiveTooDeep(1))
+    ));
+    let options = DocOptions::default().with_max_archive_entries(3);
+    assert!(matches!(
+      Doc::with_options("sample.zip", &buffer, options),
+      Err(LoaderError::TooManyEntries(3))
+    ));
+    let options = DocOptions::default().with_max_archive_size(64);
+    assert!(matches!(
+      Doc::with_options("sample.zip", &buffer, options),
+      Err(LoaderError::ArchiveTooLarge(64))
+    ));
+
+    // entries named like a file on disk are not detected as that file
+    let path = get_fixtures().join("sample.pdf");
+    let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+    writer
+      .start_file(
+        path.to_string_lossy(),
+        zip::write::SimpleFileOptions::default(),
+      )
+      .unwrap();
+    writer.write_all(b"Plain text in disguise.").unwrap();
+    let buffer = writer.finish().unwrap().into_inner();
+    let doc = Doc::new("disguised.zip", &buffer).unwrap();
+    assert_eq!(doc.chunks.len(), 1);
+    assert_eq!(doc.chunks[0].content, "Plain text in disguise.");
+  }
 }
@@ -1,4 +1,4 @@
-use std::{io, str::Utf8Error, string::FromUtf8Error};
+use std::{io, str::Utf8Error, string::FromUtf8Error, time::Duration};
 
 use thiserror::Error;
 
@@ -27,6 +27,27 @@ pub enum LoaderError {
   #[error(transparent)]
   PdfExtractOutput(#[from] pdf_extract::OutputError),
 
+  #[error(transparent)]
+  Spreadsheet(#[from] calamine::Error),
+
+  #[error(transparent)]
+  Csv(#[from] csv::Error),
+
+  #[error(transparent)]
+  Zip(#[from] zip::result::ZipError),
+
+  #[error(transparent)]
+  Xml(#[from] quick_xml::Error),
+
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+
+  #[error(transparent)]
+  Yaml(#[from] serde_yaml_ng::Error),
+
+  #[error(transparent)]
+  Toml(#[from] toml::de::Error),
+
   #[error(transparent)]
   Readability(#[from] readability::error::Error),
 
@@ -36,8 +57,59 @@ pub enum LoaderError {
   #[error("Unsupported source language")]
   UnsupportedLanguage,
 
+  #[error("Document is encrypted, a password is required")]
+  PasswordRequired,
+
+  #[error("Incorrect password for encrypted document")]
+  IncorrectPassword,
+
+  #[error("Archive has more than {0} entries")]
+  TooManyEntries(usize),
+
+  #[error("Archive expands to more than {0} bytes")]
+  ArchiveTooLarge(u64),
+
+  #[error("Archive is nested more than {0} levels deep")]
+  ArchiveTooDeep(usize),
+
+  #[error("Sheet has more than {0} cells")]
+  SheetTooLarge(u64),
+
+  #[error("Parsing took longer than {0:?}")]
+  Timeout(Duration),
+
+  #[error("Parsing was aborted")]
+  Aborted,
+
+  #[error("Document has more than {0} bytes of text")]
+  TextTooLarge(usize),
+
+  #[error("Document has more than {0} chunks")]
+  TooManyChunks(usize),
+
+  #[error("Dedup threshold {0} is not above 0 and at most 1")]
+  InvalidDedupThreshold(f64),
+
   #[error("Error: {0}")]
   Other(String),
 }
 
+impl LoaderError {
+  /// Whether a limit protecting against archive bombs was exceeded.
+  pub fn is_archive_limit(&self) -> bool {
+    matches!(
+      self,
+      Self::TooManyEntries(_) | Self::ArchiveTooLarge(_) | Self::ArchiveTooDeep(_)
+    )
+  }
+
+  /// Whether a limit of the time or output of the parse was exceeded.
+  pub fn is_parse_limit(&self) -> bool {
+    matches!(
+      self,
+      Self::Timeout(_) | Self::TextTooLarge(_) | Self::TooManyChunks(_)
+    )
+  }
+}
+
 pub type LoaderResult<T> = Result<T, LoaderError>;
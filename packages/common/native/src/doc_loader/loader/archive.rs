@@ -0,0 +1,197 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use super::*;
+
+/// Limits on the entries read from the archives of a document, nested
+/// archives share the budget of the archive they are found in. Sizes are
+/// checked against the bytes actually read rather than the sizes an archive
+/// claims.
+#[derive(Debug)]
+pub struct ArchiveBudget {
+  max_entries: usize,
+  max_size: u64,
+  entries: AtomicUsize,
+  size: AtomicU64,
+}
+
+impl ArchiveBudget {
+  pub fn new(max_entries: usize, max_size: u64) -> Self {
+    Self {
+      max_entries,
+      max_size,
+      entries: AtomicUsize::new(0),
+      size: AtomicU64::new(0),
+    }
+  }
+
+  pub(super) fn read<R: Read>(&self, reader: R) -> LoaderResult<Vec<u8>> {
+    let remaining = self.count_entry()?;
+    // read one byte more than allowed to detect entries over the limit
+    let mut content = vec![];
+    reader.take(remaining.saturating_add(1)).read_to_end(&mut content)?;
+    self.add_size(content.len() as u64, remaining)?;
+    Ok(content)
+  }
+
+  /// Counts a file that was extracted from a document before, like the
+  /// attachments of emails.
+  pub fn charge(&self, size: u64) -> LoaderResult<()> {
+    let remaining = self.count_entry()?;
+    self.add_size(size, remaining)
+  }
+
+  /// Counts an entry, returning the number of bytes that may still be read.
+  fn count_entry(&self) -> LoaderResult<u64> {
+    if self.entries.fetch_add(1, Ordering::Relaxed) >= self.max_entries {
+      return Err(LoaderError::TooManyEntries(self.max_entries));
+    }
+    Ok(
+      self
+        .max_size
+        .saturating_sub(self.size.load(Ordering::Relaxed)),
+    )
+  }
+
+  fn add_size(&self, size: u64, remaining: u64) -> LoaderResult<()> {
+    if size > remaining {
+      return Err(LoaderError::ArchiveTooLarge(self.max_size));
+    }
+    self.size.fetch_add(size, Ordering::Relaxed);
+    Ok(())
+  }
+}
+
+/// A file of an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+  pub path: String,
+  pub content: Vec<u8>,
+}
+
+/// The files of a ZIP, tar or gzip archive, which are loaded as documents of
+/// their own.
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+  pub entries: Vec<ArchiveEntry>,
+}
+
+/// Metadata that operating systems leave in archives.
+fn is_hidden(path: &str) -> bool {
+  path.split('/').any(|p| p == "__MACOSX" || p == ".DS_Store")
+}
+
+impl Archive {
+  pub fn from_zip<R: Read + Seek>(reader: R, budget: &ArchiveBudget) -> LoaderResult<Self> {
+    let mut zip = ZipArchive::new(reader)?;
+    let mut archive = Self::default();
+    for i in 0..zip.len() {
+      let file = zip.by_index(i)?;
+      if file.is_dir() || is_hidden(file.name()) {
+        continue;
+      }
+      let path = file.name().to_string();
+      archive.push(path, file, budget)?;
+    }
+    Ok(archive)
+  }
+
+  pub fn from_tar<R: Read>(reader: R, budget: &ArchiveBudget) -> LoaderResult<Self> {
+    let mut tar = tar::Archive::new(reader);
+    let mut archive = Self::default();
+    for entry in tar.entries()? {
+      let entry = entry?;
+      let path = entry.path()?.to_string_lossy().into_owned();
+      if !entry.header().entry_type().is_file() || is_hidden(&path) {
+        continue;
+      }
+      archive.push(path, entry, budget)?;
+    }
+    Ok(archive)
+  }
+
+  /// Reads a gzip file, which is either a compressed tar archive or a single
+  /// compressed file named like the archive without the `.gz` extension.
+  pub fn from_gzip<R: Read>(reader: R, name: &str, budget: &ArchiveBudget) -> LoaderResult<Self> {
+    let mut decoder = GzDecoder::new(reader);
+    // a tar archive is recognized by the header of its first entry
+    let mut header = vec![];
+    (&mut decoder).take(512).read_to_end(&mut header)?;
+    let reader = header.as_slice().chain(decoder);
+    if infer::get(&header).is_some_and(|kind| kind.extension() == "tar") {
+      return Self::from_tar(reader, budget);
+    }
+
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let mut archive = Self::default();
+    archive.push(name.to_string(), reader, budget)?;
+    Ok(archive)
+  }
+
+  fn push<R: Read>(&mut self, path: String, reader: R, budget: &ArchiveBudget) -> LoaderResult<()> {
+    let content = budget.read(reader)?;
+    self.entries.push(ArchiveEntry { path, content });
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Cursor, Write};
+
+  use zip::{write::SimpleFileOptions, ZipWriter};
+
+  use super::*;
+
+  fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    for (name, content) in files {
+      writer
+        .start_file(*name, SimpleFileOptions::default())
+        .unwrap();
+      writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+  }
+
+  #[test]
+  fn test_zip_entries() {
+    let buffer = zip(&[
+      ("docs/readme.md", b"# Readme"),
+      ("__MACOSX/docs/._readme.md", b"junk"),
+      ("src/main.rs", b"fn main() {}"),
+    ]);
+    let budget = ArchiveBudget::new(10, 1024);
+    let archive = Archive::from_zip(Cursor::new(buffer), &budget).unwrap();
+    let paths = archive
+      .entries
+      .iter()
+      .map(|e| e.path.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["docs/readme.md", "src/main.rs"]);
+    assert_eq!(budget.size.load(Ordering::Relaxed), 20);
+  }
+
+  #[test]
+  fn test_zip_limits() {
+    let buffer = zip(&[("a.txt", b"a"), ("b.txt", b"b"), ("c.txt", b"c")]);
+    assert!(matches!(
+      Archive::from_zip(Cursor::new(buffer), &ArchiveBudget::new(2, 1024)),
+      Err(LoaderError::TooManyEntries(2))
+    ));
+
+    // highly compressible content expanding past the size limit
+    let buffer = zip(&[("bomb.txt", &[0; 4096])]);
+    assert!(matches!(
+      Archive::from_zip(Cursor::new(buffer), &ArchiveBudget::new(10, 1024)),
+      Err(LoaderError::ArchiveTooLarge(1024))
+    ));
+
+    // a budget without a size limit reads entries whole
+    let budget = ArchiveBudget::new(10, u64::MAX);
+    assert_eq!(budget.read(&b"abc"[..]).unwrap(), b"abc");
+  }
+}
@@ -0,0 +1,24 @@
+/// The blocks of a markdown document converted from a word processor format,
+/// consecutive list items are kept together as one list.
+#[derive(Default)]
+pub(super) struct Blocks {
+  blocks: Vec<String>,
+  last_list: bool,
+}
+
+impl Blocks {
+  pub fn push(&mut self, block: String, list: bool) {
+    match self.blocks.last_mut() {
+      Some(last) if list && self.last_list => {
+        last.push('\n');
+        last.push_str(&block);
+      }
+      _ => self.blocks.push(block),
+    }
+    self.last_list = list;
+  }
+
+  pub fn into_markdown(self) -> String {
+    self.blocks.join("\n\n")
+  }
+}
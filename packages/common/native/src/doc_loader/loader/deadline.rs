@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use super::*;
+
+/// Point in time after which parsing a document fails, documents embedded in
+/// it share the deadline of the outermost one. Loaders check it between the
+/// pages or entries they read, so parsing stops at the next one once the
+/// deadline passed.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+  timeout: Duration,
+  at: Instant,
+}
+
+impl Deadline {
+  pub fn new(timeout: Duration) -> Self {
+    Self {
+      timeout,
+      at: Instant::now() + timeout,
+    }
+  }
+
+  pub fn check(&self) -> LoaderResult<()> {
+    if Instant::now() >= self.at {
+      return Err(LoaderError::Timeout(self.timeout));
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::thread::sleep;
+
+  use super::*;
+
+  #[test]
+  fn test_deadline() {
+    let deadline = Deadline::new(Duration::from_millis(1));
+    sleep(Duration::from_millis(5));
+    assert!(matches!(deadline.check(), Err(LoaderError::Timeout(_))));
+    assert!(Deadline::new(Duration::ZERO).check().is_err());
+    assert!(Deadline::new(Duration::from_secs(60)).check().is_ok());
+  }
+}
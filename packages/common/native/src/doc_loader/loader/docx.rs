@@ -1,19 +1,160 @@
+use std::{collections::HashMap, ops::Range};
+
 use docx_parser::MarkdownDocument;
+use serde_json::Value;
 
-use super::*;
+use super::{package::Package, *};
 
 #[derive(Debug)]
 pub struct DocxLoader {
   document: MarkdownDocument,
+  split_sections: bool,
+  table_options: SplitterOptions,
+}
+
+#[derive(Debug, PartialEq)]
+enum Block {
+  Heading(usize, String),
+  Table,
+  List,
+  Paragraph,
+}
+
+impl Block {
+  fn kind(&self) -> &'static str {
+    match self {
+      Block::Heading(..) => "heading",
+      Block::Table => "table",
+      Block::List => "list",
+      Block::Paragraph => "paragraph",
+    }
+  }
+}
+
+fn is_list_item(line: &str) -> bool {
+  let line = line.trim_start();
+  let Some((marker, _)) = line.split_once(' ') else {
+    return false;
+  };
+  matches!(marker, "-" | "*" | "+")
+    || marker.len() > 1
+      && marker.len() <= 4
+      && marker.ends_with(['.', ')'])
+      && marker[..marker.len() - 1]
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Splits the markdown into blocks separated by blank lines, tables are
+/// contiguous `|` lines and headings always stand on their own line.
+fn parse_blocks(markdown: &str) -> Vec<(Range<usize>, Block)> {
+  let mut blocks: Vec<(Range<usize>, Block)> = vec![];
+  let mut offset = 0;
+  for line in markdown.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    let content = line.trim_end();
+    let range = start..start + content.len();
+    if content.trim().is_empty() {
+      continue;
+    }
+
+    if let Some((level, text)) = parse_heading(content) {
+      blocks.push((range, Block::Heading(level, text)));
+      continue;
+    }
+
+    let block = if content.trim_start().starts_with('|') {
+      Block::Table
+    } else if is_list_item(content) {
+      Block::List
+    } else {
+      Block::Paragraph
+    };
+    match blocks.last_mut() {
+      // continue the previous block if no blank line separates them
+      Some((prev, kind))
+        if markdown[prev.end..start].trim_matches([' ', '\t', '\r']) == "\n"
+          && !matches!(kind, Block::Heading(..))
+          && (*kind == Block::Table) == (block == Block::Table) =>
+      {
+        prev.end = range.end;
+      }
+      _ => blocks.push((range, block)),
+    }
+  }
+  blocks
+}
+
+#[derive(Default)]
+struct Section {
+  range: Option<Range<usize>>,
+  block_types: Vec<&'static str>,
+  heading_path: Vec<String>,
+  has_body: bool,
+}
+
+impl Section {
+  fn push(&mut self, range: &Range<usize>, block: &Block) {
+    self.range = Some(match self.range.take() {
+      Some(current) => current.start..range.end,
+      None => range.clone(),
+    });
+    if !self.block_types.contains(&block.kind()) {
+      self.block_types.push(block.kind());
+    }
+    self.has_body |= !matches!(block, Block::Heading(..));
+  }
+
+  fn into_document(self, markdown: &str) -> Option<Document> {
+    let range = self.range?;
+    Some(
+      Document::new(&markdown[range.clone()]).with_metadata(HashMap::from([
+        ("start".into(), Value::from(range.start)),
+        (
+          "line_start".into(),
+          Value::from(markdown[..range.start].matches('\n').count() + 1),
+        ),
+        ("heading_path".into(), Value::from(self.heading_path)),
+        ("block_types".into(), Value::from(self.block_types)),
+      ])),
+    )
+  }
 }
 
 impl DocxLoader {
   pub fn new<R: Read + Seek>(reader: R) -> Option<Self> {
     Some(Self {
       document: MarkdownDocument::from_reader(reader)?,
+      split_sections: false,
+      table_options: SplitterOptions::default(),
     })
   }
 
+  /// Reads the title, author and dates of a document from its
+  /// `docProps/core.xml` part.
+  pub fn read_metadata<R: Read + Seek>(reader: R) -> LoaderResult<DocMetadata> {
+    match Package::new(reader)?.read("docProps/core.xml")? {
+      Some(core) => read_properties(&core),
+      None => Ok(DocMetadata::default()),
+    }
+  }
+
+  /// Emit one document per heading section instead of a single document,
+  /// tables are emitted as documents of their own so they are only split if
+  /// they do not fit a chunk.
+  pub fn with_split_sections(mut self, split_sections: bool) -> Self {
+    self.split_sections = split_sections;
+    self
+  }
+
+  /// Chunk size tables are measured against, tables larger than a chunk are
+  /// split into groups of rows repeating the header row.
+  pub fn with_table_options(mut self, table_options: SplitterOptions) -> Self {
+    self.table_options = table_options;
+    self
+  }
+
   fn extract_text(&self) -> String {
     self.document.to_markdown(false)
   }
@@ -21,12 +162,83 @@ impl DocxLoader {
   fn extract_text_to_doc(&self) -> Document {
     Document::new(self.extract_text())
   }
+
+  fn extract_sections_to_docs(&self) -> Vec<Document> {
+    let markdown = self.extract_text();
+    let mut documents = vec![];
+    let mut headings: Vec<(usize, String)> = vec![];
+    let mut section = Section::default();
+    for (range, block) in parse_blocks(&markdown) {
+      match &block {
+        Block::Heading(level, text) => {
+          // a heading directly followed by a sub heading stays in the same
+          // section, so the parent heading is not emitted as an empty chunk
+          let nested = !section.has_body && headings.last().is_some_and(|(l, _)| l < level);
+          if !nested {
+            documents.extend(std::mem::take(&mut section).into_document(&markdown));
+          }
+          headings.retain(|(l, _)| l < level);
+          headings.push((*level, text.clone()));
+          section.heading_path = headings.iter().map(|(_, text)| text.clone()).collect();
+          section.push(&range, &block);
+        }
+        Block::Table => {
+          let heading_path = section.heading_path.clone();
+          documents.extend(std::mem::take(&mut section).into_document(&markdown));
+          let mut table = Section {
+            heading_path: heading_path.clone(),
+            ..Default::default()
+          };
+          table.push(&range, &block);
+          documents.extend(table.into_document(&markdown));
+          section.heading_path = heading_path;
+        }
+        Block::List | Block::Paragraph => section.push(&range, &block),
+      }
+    }
+    documents.extend(section.into_document(&markdown));
+    documents
+  }
+}
+
+fn is_table(document: &Document) -> bool {
+  document.metadata.get("block_types") == Some(&Value::from(vec!["table"]))
 }
 
 impl Loader for DocxLoader {
   fn load(self) -> LoaderResult<Vec<Document>> {
-    let doc = self.extract_text_to_doc();
-    Ok(vec![doc])
+    if self.split_sections {
+      Ok(self.extract_sections_to_docs())
+    } else {
+      let doc = self.extract_text_to_doc();
+      Ok(vec![doc])
+    }
+  }
+
+  fn load_and_split<TS: TextSplitter + 'static>(self, splitter: TS) -> LoaderResult<Vec<Document>>
+  where
+    Self: Sized,
+  {
+    let chunk_size = self.table_options.chunk_size;
+    let bpe = self.table_options.get_bpe()?;
+    let table_splitter = TableSplitter::new(self.table_options.clone());
+    let mut documents = vec![];
+    for mut doc in self.load()? {
+      if is_table(&doc) {
+        if bpe.encode_ordinary(&doc.page_content).len() > chunk_size {
+          documents.extend(table_splitter.split_documents(&[doc])?);
+          continue;
+        }
+        if let Some(start) = doc.metadata.get("start").and_then(Value::as_u64) {
+          let end = start as usize + doc.page_content.len();
+          doc.metadata.insert("end".into(), Value::from(end));
+        }
+        documents.push(doc);
+      } else {
+        documents.extend(splitter.split_documents(&[doc])?);
+      }
+    }
+    Ok(documents)
   }
 }
 
@@ -55,8 +267,10 @@ mod tests {
     }
 
     {
-      let loader = DocxLoader::new(Cursor::new(docx_buffer)).unwrap();
-      let documents = loader.load_and_split(TokenSplitter::default()).unwrap();
+      let loader = DocxLoader::new(Cursor::new(docx_buffer))
+        .unwrap()
+        .with_split_sections(true);
+      let documents = loader.load_and_split(MarkdownSplitter::default()).unwrap();
 
       for (idx, doc) in documents.into_iter().enumerate() {
         assert_eq!(
@@ -68,4 +282,93 @@ mod tests {
       }
     }
   }
+
+  #[test]
+  fn test_parse_docx_sections() {
+    let docx_buffer = include_bytes!("../../../fixtures/demo.docx");
+    let parsed_buffer = include_str!("../../../fixtures/demo.docx.md");
+
+    let loader = DocxLoader::new(Cursor::new(docx_buffer))
+      .unwrap()
+      .with_split_sections(true);
+    let documents = loader.load_and_split(MarkdownSplitter::default()).unwrap();
+
+    let tables = documents.iter().filter(|d| is_table(d)).collect::<Vec<_>>();
+    assert_eq!(tables.len(), 5);
+    assert!(tables[0]
+      .page_content
+      .contains("| Scissors    | 1 pair   |"));
+    assert_eq!(
+      tables[0].metadata["heading_path"],
+      Value::from(vec!["Tables"])
+    );
+
+    let formatting = documents
+      .iter()
+      .find(|d| d.page_content.contains("Paragraph level formatting"))
+      .unwrap();
+    assert_eq!(
+      formatting.metadata["heading_path"],
+      Value::from(vec!["Text Formatting", "Paragraph level formatting"])
+    );
+
+    let lists = documents
+      .iter()
+      .find(|d| d.page_content.contains("- Two"))
+      .unwrap();
+    assert_eq!(
+      lists.metadata["heading_path"],
+      Value::from(vec!["Lists", "Bulleted List"])
+    );
+    assert_eq!(
+      lists.metadata["block_types"],
+      Value::from(vec!["heading", "list"])
+    );
+
+    for doc in documents {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert_eq!(&parsed_buffer[start..end], doc.page_content);
+    }
+  }
+
+  #[test]
+  fn test_parse_docx_large_tables() {
+    let docx_buffer = include_bytes!("../../../fixtures/demo.docx");
+    let parsed_buffer = include_str!("../../../fixtures/demo.docx.md");
+
+    let options = SplitterOptions::default()
+      .with_chunk_size(64)
+      .with_chunk_overlap(0);
+    let loader = DocxLoader::new(Cursor::new(docx_buffer))
+      .unwrap()
+      .with_split_sections(true)
+      .with_table_options(options.clone());
+    let documents = loader
+      .load_and_split(MarkdownSplitter::new(options))
+      .unwrap();
+
+    let tables = documents.iter().filter(|d| is_table(d)).collect::<Vec<_>>();
+    assert!(tables.len() > 5);
+    // every part of the calendar repeats its header
+    let calendar = tables
+      .iter()
+      .filter(|d| {
+        d.page_content
+          .starts_with("|               |  |     |  |     |")
+      })
+      .collect::<Vec<_>>();
+    assert!(calendar.len() > 1);
+    assert!(calendar[0].page_content.contains("December 2007"));
+    let mut next_row = 1;
+    for doc in calendar {
+      assert_eq!(doc.metadata["row_start"].as_u64(), Some(next_row));
+      next_row = doc.metadata["row_end"].as_u64().unwrap() + 1;
+    }
+    for doc in &tables {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert!(doc.page_content.ends_with(&parsed_buffer[start..end]));
+    }
+  }
 }
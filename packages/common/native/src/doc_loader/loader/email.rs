@@ -0,0 +1,210 @@
+use std::{collections::HashMap, io::BufRead};
+
+use mail_parser::{mailbox::mbox::MessageIterator, Addr, Address, MessageParser, MimeHeaders};
+use serde_json::Value;
+
+use super::*;
+
+/// A file attached to a message, which is loaded as a document of its own.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+  pub name: String,
+  pub content: Vec<u8>,
+  /// Index of the message the file is attached to
+  pub message_index: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Message {
+  content: String,
+  metadata: HashMap<String, Value>,
+}
+
+/// Loads RFC 822 messages (`.eml`) and mbox archives, one document per
+/// message with its sender, recipients, subject and date as metadata.
+#[derive(Debug, Clone)]
+pub struct EmailLoader {
+  messages: Vec<Message>,
+  attachments: Vec<Attachment>,
+}
+
+fn format_address(addr: &Addr) -> Option<String> {
+  match (addr.name(), addr.address()) {
+    (Some(name), Some(address)) => Some(format!("{} <{}>", name, address)),
+    (name, address) => name.or(address).map(ToString::to_string),
+  }
+}
+
+fn addresses(address: Option<&Address>) -> Vec<String> {
+  address
+    .map(|address| address.iter().filter_map(format_address).collect())
+    .unwrap_or_default()
+}
+
+impl EmailLoader {
+  pub fn new(raw: &[u8]) -> LoaderResult<Self> {
+    let mut loader = Self {
+      messages: vec![],
+      attachments: vec![],
+    };
+    loader.push_message(raw)?;
+    Ok(loader)
+  }
+
+  pub fn from_mbox<R: BufRead>(reader: R) -> LoaderResult<Self> {
+    let mut loader = Self {
+      messages: vec![],
+      attachments: vec![],
+    };
+    for message in MessageIterator::new(reader) {
+      loader.push_message(message?.contents())?;
+    }
+    Ok(loader)
+  }
+
+  /// Takes the attachments of all messages, they are not part of the
+  /// documents returned by `load`.
+  pub fn take_attachments(&mut self) -> Vec<Attachment> {
+    std::mem::take(&mut self.attachments)
+  }
+
+  fn push_message(&mut self, raw: &[u8]) -> LoaderResult<()> {
+    let message = MessageParser::default()
+      .parse(raw)
+      .ok_or(LoaderError::Other("Failed to parse email".into()))?;
+    let message_index = self.messages.len();
+
+    // text parts stand in for the html body if there is none
+    let html = message
+      .html_bodies()
+      .filter(|part| part.is_text_html())
+      .filter_map(|part| part.text_contents())
+      .map(|html| {
+        let url = Url::parse("email://message/")?;
+        let docs = HtmlLoader::from_string(html, url).load()?;
+        Ok(
+          docs
+            .into_iter()
+            .map(|doc| doc.page_content)
+            .collect::<String>(),
+        )
+      })
+      .collect::<LoaderResult<Vec<_>>>()?;
+    let bodies = if html.iter().any(|body| !body.trim().is_empty()) {
+      html
+    } else {
+      message
+        .text_bodies()
+        .filter_map(|part| part.text_contents())
+        .map(ToString::to_string)
+        .collect()
+    };
+    let content = bodies
+      .iter()
+      .map(|body| body.trim())
+      .filter(|body| !body.is_empty())
+      .collect::<Vec<_>>()
+      .join("\n\n");
+
+    let mut metadata = HashMap::from([
+      ("message_index".to_string(), Value::from(message_index)),
+      ("to".to_string(), Value::from(addresses(message.to()))),
+    ]);
+    if let Some(from) = message
+      .from()
+      .and_then(|from| from.first())
+      .and_then(format_address)
+    {
+      metadata.insert("from".to_string(), Value::from(from));
+    }
+    if let Some(subject) = message.subject() {
+      metadata.insert("subject".to_string(), Value::from(subject));
+    }
+    if let Some(date) = message.date() {
+      metadata.insert("date".to_string(), Value::from(date.to_rfc3339()));
+    }
+    self.messages.push(Message { content, metadata });
+
+    for part in message.attachments() {
+      let name = match part.attachment_name() {
+        Some(name) => name.to_string(),
+        // forwarded messages are usually attached without a file name
+        None if part.is_message() => "message.eml".to_string(),
+        None => continue,
+      };
+      self.attachments.push(Attachment {
+        name,
+        content: part.contents().to_vec(),
+        message_index,
+      });
+    }
+    Ok(())
+  }
+}
+
+impl Loader for EmailLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let mut offset = 0;
+    Ok(
+      self
+        .messages
+        .into_iter()
+        .map(|message| {
+          // messages are laid out one after another in the extracted text
+          let mut metadata = message.metadata;
+          metadata.insert("start".to_string(), Value::from(offset));
+          offset += message.content.len();
+          Document::new(message.content).with_metadata(metadata)
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_eml() {
+    let buffer = include_bytes!("../../../fixtures/sample.eml");
+    let mut loader = EmailLoader::new(buffer).unwrap();
+
+    let attachments = loader.take_attachments();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].name, "notes.md");
+    assert!(attachments[0].content.starts_with(b"# Launch notes"));
+
+    let documents = loader.load().unwrap();
+    assert_eq!(documents.len(), 1);
+    let metadata = &documents[0].metadata;
+    assert_eq!(metadata["from"], "Alice Martin <alice@example.com>");
+    assert_eq!(
+      metadata["to"],
+      Value::from(vec!["Bob Chen <bob@example.com>", "team@example.com"])
+    );
+    assert_eq!(metadata["subject"], "Launch plan");
+    assert_eq!(metadata["date"], "2024-03-05T09:30:00+01:00");
+    assert!(documents[0]
+      .page_content
+      .contains("We are moving the launch to Thursday"));
+  }
+
+  #[test]
+  fn test_parse_mbox() {
+    let buffer = include_bytes!("../../../fixtures/sample.mbox");
+    let mut loader = EmailLoader::from_mbox(&buffer[..]).unwrap();
+    assert!(loader.take_attachments().is_empty());
+
+    let documents = loader.load().unwrap();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].metadata["subject"], "Re: Launch plan");
+    assert_eq!(documents[0].page_content, "Thursday works for me.");
+    assert_eq!(documents[1].metadata["message_index"], 1);
+    assert_eq!(documents[1].metadata["start"], 22);
+    assert_eq!(
+      documents[1].page_content,
+      "From the archive: the old launch checklist is attached to the wiki."
+    );
+  }
+}
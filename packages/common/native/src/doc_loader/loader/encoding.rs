@@ -0,0 +1,147 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Share of the code units of UTF-16 text without a byte order mark that must
+/// have a zero high byte, as Latin text does.
+const UTF16_ZERO_RATIO: f64 = 0.3;
+
+/// Share of control characters above which text decoded with a guessed
+/// encoding is taken for binary
+const MAX_CONTROL_RATIO: f64 = 0.05;
+
+/// Decodes text files, returning the text and the name of its encoding, or
+/// `None` for binary files.
+///
+/// A byte order mark decides the encoding. UTF-16 text without one is
+/// recognized by the zero bytes of its code units, which are valid UTF-8 for
+/// Latin text, then UTF-8 is tried. Other text is decoded with the legacy
+/// encoding guessed from its byte statistics, such as Shift_JIS, GBK or
+/// windows-1252. Binary data decodes with most guessed encodings, so their
+/// text is rejected when it has too many control characters.
+pub fn decode_text(bytes: &[u8]) -> Option<(String, &'static str)> {
+  if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+    let text = encoding.decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])?;
+    return Some((text.into_owned(), encoding.name()));
+  }
+  if let Some(encoding) = utf16_without_bom(bytes) {
+    return decode_guessed(encoding, bytes);
+  }
+  if let Ok(text) = std::str::from_utf8(bytes) {
+    return Some((text.to_string(), UTF_8.name()));
+  }
+  // legacy encodings have no use for NUL bytes
+  if bytes.contains(&0) {
+    return None;
+  }
+
+  let mut detector = EncodingDetector::new();
+  detector.feed(bytes, true);
+  decode_guessed(detector.guess(None, false), bytes)
+}
+
+fn decode_guessed(encoding: &'static Encoding, bytes: &[u8]) -> Option<(String, &'static str)> {
+  let text = encoding.decode_without_bom_handling_and_without_replacement(bytes)?;
+  is_text(&text).then(|| (text.into_owned(), encoding.name()))
+}
+
+/// Returns the byte order of UTF-16 text by the position of its zero bytes.
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+  if bytes.len() < 2 || bytes.len() % 2 != 0 {
+    return None;
+  }
+  let units = bytes.len() / 2;
+  let (mut even, mut odd) = (0, 0);
+  for pair in bytes.chunks_exact(2) {
+    even += (pair[0] == 0 && pair[1] != 0) as usize;
+    odd += (pair[1] == 0 && pair[0] != 0) as usize;
+  }
+  let ratio = |zeros: usize| zeros as f64 / units as f64;
+  // characters such as U+0100 have a zero low byte, they are rare though
+  if ratio(odd) >= UTF16_ZERO_RATIO && even * 10 < odd {
+    Some(UTF_16LE)
+  } else if ratio(even) >= UTF16_ZERO_RATIO && odd * 10 < even {
+    Some(UTF_16BE)
+  } else {
+    None
+  }
+}
+
+fn is_text(text: &str) -> bool {
+  let (mut chars, mut controls) = (0, 0);
+  for c in text.chars() {
+    chars += 1;
+    controls += (c.is_control() && !c.is_whitespace()) as usize;
+  }
+  controls as f64 <= chars as f64 * MAX_CONTROL_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn encode(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+    encoding.encode(text).0.into_owned()
+  }
+
+  #[test]
+  fn test_decode_text() {
+    let japanese = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。";
+    let chinese = "天下大势，分久必合，合久必分。周末七国分争，并入于秦。";
+    let french = "Le cœur a ses raisons que la raison ne connaît point. Déjà vu, à la française.";
+
+    assert_eq!(
+      decode_text(japanese.as_bytes()),
+      Some((japanese.to_string(), "UTF-8"))
+    );
+    assert_eq!(
+      decode_text(&encode(encoding_rs::SHIFT_JIS, japanese)),
+      Some((japanese.to_string(), "Shift_JIS"))
+    );
+    assert_eq!(
+      decode_text(&encode(encoding_rs::GBK, chinese)),
+      Some((chinese.to_string(), "GBK"))
+    );
+    assert_eq!(
+      decode_text(&encode(encoding_rs::WINDOWS_1252, french)),
+      Some((french.to_string(), "windows-1252"))
+    );
+
+    let utf16 = |text: &str, be: bool| {
+      text
+        .encode_utf16()
+        .flat_map(|unit| match be {
+          true => unit.to_be_bytes(),
+          false => unit.to_le_bytes(),
+        })
+        .collect::<Vec<_>>()
+    };
+    let english = "Plain text saved by an old editor.";
+    assert_eq!(
+      decode_text(&[&[0xfe, 0xff][..], &utf16(japanese, true)].concat()),
+      Some((japanese.to_string(), "UTF-16BE"))
+    );
+    assert_eq!(
+      decode_text(&utf16(english, true)),
+      Some((english.to_string(), "UTF-16BE"))
+    );
+    assert_eq!(
+      decode_text(&utf16(english, false)),
+      Some((english.to_string(), "UTF-16LE"))
+    );
+    assert_eq!(
+      decode_text(&[0xef, 0xbb, 0xbf, b'o', b'k']),
+      Some(("ok".to_string(), "UTF-8"))
+    );
+
+    // control characters of valid UTF-8, such as the colors of logs, are kept
+    let log = "\x1b[31merror\x1b[0m: disk full\n\x1b[33mwarn\x1b[0m: retrying\n";
+    assert_eq!(
+      decode_text(log.as_bytes()),
+      Some((log.to_string(), "UTF-8"))
+    );
+
+    // binary data is not taken for text
+    assert_eq!(decode_text(&[0x00, 0x01, 0x02, 0x03, 0xff, 0x10]), None);
+    assert_eq!(decode_text(&(0..=255).collect::<Vec<u8>>()), None);
+  }
+}
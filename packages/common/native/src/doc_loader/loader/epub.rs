@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use quick_xml::{events::Event, Reader};
+use serde_json::Value;
+
+use super::{
+  package::{attribute, resolve_path, Package},
+  *,
+};
+
+#[derive(Debug, Clone)]
+struct Chapter {
+  path: String,
+  spine_index: usize,
+  title: Option<String>,
+  html: String,
+}
+
+/// Loads EPUB books, one document per chapter of the spine.
+#[derive(Debug, Clone)]
+pub struct EpubLoader {
+  chapters: Vec<Chapter>,
+}
+
+#[derive(Default)]
+struct ManifestItem {
+  href: String,
+  media_type: String,
+  properties: String,
+}
+
+fn parent(path: &str) -> &str {
+  path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+fn without_fragment(href: &str) -> &str {
+  href.split_once('#').map_or(href, |(path, _)| path)
+}
+
+impl EpubLoader {
+  pub fn new<R: Read + Seek>(reader: R) -> LoaderResult<Self> {
+    let mut package = Package::new(reader)?;
+    let container = package
+      .read("META-INF/container.xml")?
+      .ok_or(LoaderError::Other("Missing EPUB container".into()))?;
+    let opf_path = read_rootfile(&container)?
+      .ok_or(LoaderError::Other("Missing EPUB package document".into()))?;
+    let opf = package
+      .read(&opf_path)?
+      .ok_or(LoaderError::Other("Missing EPUB package document".into()))?;
+    let (manifest, spine, toc_id) = read_opf(&opf, parent(&opf_path))?;
+
+    // chapter titles come from the EPUB 3 navigation document or the NCX
+    let nav = manifest
+      .values()
+      .find(|item| item.properties.split_whitespace().any(|p| p == "nav"));
+    let toc = match (nav, toc_id.and_then(|id| manifest.get(&id))) {
+      (Some(nav), _) => package
+        .read(&nav.href)?
+        .map(|xml| read_toc(&xml, parent(&nav.href)))
+        .transpose()?,
+      (None, Some(ncx)) => package
+        .read(&ncx.href)?
+        .map(|xml| read_toc(&xml, parent(&ncx.href)))
+        .transpose()?,
+      _ => None,
+    }
+    .unwrap_or_default();
+
+    let mut chapters = vec![];
+    for (spine_index, idref) in spine.into_iter().enumerate() {
+      let Some(item) = manifest.get(&idref) else {
+        continue;
+      };
+      if !item.media_type.contains("html") {
+        continue;
+      }
+      let Some(html) = package.read(&item.href)? else {
+        continue;
+      };
+      chapters.push(Chapter {
+        path: item.href.clone(),
+        spine_index,
+        title: toc.get(&item.href).cloned(),
+        html,
+      });
+    }
+    Ok(Self { chapters })
+  }
+}
+
+fn read_rootfile(container: &str) -> LoaderResult<Option<String>> {
+  let mut reader = Reader::from_str(container);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"rootfile" => {
+        return attribute(&e, "full-path");
+      }
+      Event::Eof => return Ok(None),
+      _ => {}
+    }
+  }
+}
+
+/// Reads the manifest items by id, the spine item ids and the id of the NCX.
+#[allow(clippy::type_complexity)]
+fn read_opf(
+  opf: &str,
+  dir: &str,
+) -> LoaderResult<(HashMap<String, ManifestItem>, Vec<String>, Option<String>)> {
+  let mut manifest = HashMap::new();
+  let mut spine = vec![];
+  let mut toc = None;
+  let mut reader = Reader::from_str(opf);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+        b"item" => {
+          let item = ManifestItem {
+            href: resolve_path(dir, &attribute(&e, "href")?.unwrap_or_default()),
+            media_type: attribute(&e, "media-type")?.unwrap_or_default(),
+            properties: attribute(&e, "properties")?.unwrap_or_default(),
+          };
+          if let Some(id) = attribute(&e, "id")? {
+            manifest.insert(id, item);
+          }
+        }
+        b"itemref" => spine.extend(attribute(&e, "idref")?),
+        b"spine" => toc = attribute(&e, "toc")?,
+        _ => {}
+      },
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok((manifest, spine, toc))
+}
+
+/// Maps chapter paths to the first label the table of contents gives them,
+/// either from the `a` links of a navigation document or the `navPoint`s of
+/// an NCX file.
+fn read_toc(xml: &str, dir: &str) -> LoaderResult<HashMap<String, String>> {
+  let mut toc = HashMap::new();
+  let mut insert = |href: &str, label: &str| {
+    let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !label.is_empty() {
+      toc
+        .entry(resolve_path(dir, without_fragment(href)))
+        .or_insert(label);
+    }
+  };
+
+  let mut label: Option<String> = None;
+  // NCX labels come before the link they belong to
+  let mut pending: Option<String> = None;
+  let mut href: Option<String> = None;
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) if e.local_name().as_ref() == b"a" => {
+        href = attribute(&e, "href")?;
+        label = Some(String::new());
+      }
+      Event::Start(e) if e.local_name().as_ref() == b"navLabel" => label = Some(String::new()),
+      Event::Text(e) => {
+        if let Some(label) = &mut label {
+          label.push_str(&e.unescape()?);
+        }
+      }
+      Event::End(e) if e.local_name().as_ref() == b"a" => {
+        if let (Some(label), Some(href)) = (label.take(), href.take()) {
+          insert(&href, &label);
+        }
+      }
+      Event::End(e) if e.local_name().as_ref() == b"navLabel" => pending = label.take(),
+      Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"content" => {
+        if let (Some(label), Some(src)) = (pending.take(), attribute(&e, "src")?) {
+          insert(&src, &label);
+        }
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok(toc)
+}
+
+impl Loader for EpubLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let base = Url::parse("epub://book/")?;
+    let mut documents = vec![];
+    let mut offset = 0;
+    for chapter in self.chapters {
+      let url = base.join(&chapter.path)?;
+      let Some(doc) = HtmlLoader::from_string(chapter.html, url).load()?.pop() else {
+        continue;
+      };
+      if doc.page_content.trim().is_empty() {
+        continue;
+      }
+      // readability puts the title of the page on the first line
+      let title = chapter.title.or_else(|| {
+        doc
+          .page_content
+          .lines()
+          .next()
+          .map(str::trim)
+          .filter(|title| !title.is_empty())
+          .map(ToString::to_string)
+      });
+
+      // chapters are laid out one after another in the extracted text
+      let mut metadata = HashMap::from([
+        ("spine_index".to_string(), Value::from(chapter.spine_index)),
+        ("start".to_string(), Value::from(offset)),
+      ]);
+      offset += doc.page_content.len();
+      if let Some(title) = title {
+        metadata.insert("chapter_title".to_string(), Value::from(title));
+      }
+      documents.push(Document::new(doc.page_content).with_metadata(metadata));
+    }
+    Ok(documents)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_epub() {
+    let buffer = include_bytes!("../../../fixtures/sample.epub");
+    let documents = EpubLoader::new(Cursor::new(buffer))
+      .unwrap()
+      .load()
+      .unwrap();
+
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].metadata["spine_index"], 1);
+    assert_eq!(documents[0].metadata["chapter_title"], "The Harbor");
+    assert!(documents[0]
+      .page_content
+      .contains("The fishing boats came back before dawn"));
+    assert_eq!(documents[1].metadata["spine_index"], 2);
+    assert_eq!(documents[1].metadata["chapter_title"], "The Lighthouse");
+    assert!(documents[1]
+      .page_content
+      .contains("The keeper climbed the stairs every evening"));
+  }
+}
@@ -26,6 +26,108 @@ impl<R: Read> HtmlLoader<R> {
   }
 }
 
+/// Reads the title, the language of the `<html>` element and the author,
+/// description, keywords and dates of the `<meta>` tags of the head, including
+/// their Open Graph and Dublin Core variants.
+pub fn html_metadata(html: &str) -> DocMetadata {
+  let mut metadata = DocMetadata::default();
+  let mut og_title = None;
+  let mut rest = html;
+  while let Some(open) = rest.find('<') {
+    rest = &rest[open + 1..];
+    let Some(close) = rest.find('>') else {
+      break;
+    };
+    let tag = &rest[..close];
+    rest = &rest[close + 1..];
+    let name_end = tag
+      .find(|c: char| c.is_ascii_whitespace() || c == '/')
+      .unwrap_or(tag.len());
+    let (name, attributes) = tag.split_at(name_end);
+    match name.to_ascii_lowercase().as_str() {
+      "html" => metadata.language = html_attribute(attributes, "lang"),
+      "title" if metadata.title.is_none() => {
+        let end = rest
+          .to_ascii_lowercase()
+          .find("</title")
+          .unwrap_or(rest.len());
+        let title = decode_entities(&rest[..end]);
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        metadata.title = (!title.is_empty()).then_some(title);
+        rest = &rest[end..];
+      }
+      "meta" => {
+        let Some(content) = html_attribute(attributes, "content") else {
+          continue;
+        };
+        let Some(key) =
+          html_attribute(attributes, "name").or_else(|| html_attribute(attributes, "property"))
+        else {
+          continue;
+        };
+        let field = match key.to_ascii_lowercase().as_str() {
+          "author" | "article:author" | "dc.creator" => &mut metadata.author,
+          "description" | "dc.description" => &mut metadata.subject,
+          "keywords" => &mut metadata.keywords,
+          "article:published_time" | "dcterms.created" | "date" => &mut metadata.created,
+          "article:modified_time" | "dcterms.modified" => &mut metadata.modified,
+          "og:title" | "dc.title" => &mut og_title,
+          _ => continue,
+        };
+        field.get_or_insert(content);
+      }
+      "body" => break,
+      _ => {}
+    }
+  }
+
+  metadata.title = metadata.title.or(og_title);
+  metadata.created = metadata.created.map(|date| normalize_date(&date));
+  metadata.modified = metadata.modified.map(|date| normalize_date(&date));
+  metadata
+}
+
+/// Returns the value of the attribute `name` of a tag, quoted or not.
+fn html_attribute(attributes: &str, name: &str) -> Option<String> {
+  let mut rest = attributes;
+  while let Some(eq) = rest.find('=') {
+    let key = rest[..eq]
+      .trim()
+      .rsplit(|c: char| c.is_ascii_whitespace())
+      .next()?;
+    let value = rest[eq + 1..].trim_start();
+    let (value, next) = match value.chars().next() {
+      Some(quote @ ('"' | '\'')) => {
+        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+      }
+      _ => {
+        let end = value
+          .find(|c: char| c.is_ascii_whitespace())
+          .unwrap_or(value.len());
+        (value[..end].trim_end_matches('/'), &value[end..])
+      }
+    };
+    if key.eq_ignore_ascii_case(name) {
+      let value = decode_entities(value).trim().to_string();
+      return (!value.is_empty()).then_some(value);
+    }
+    rest = next;
+  }
+  None
+}
+
+fn decode_entities(text: &str) -> String {
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&apos;", "'")
+    .replace("&nbsp;", " ")
+    .replace("&amp;", "&")
+}
+
 impl<R: Read + Send + Sync + 'static> Loader for HtmlLoader<R> {
   fn load(mut self) -> LoaderResult<Vec<Document>> {
     let cleaned_html = readability::extractor::extract(&mut self.html, &self.url)?;
@@ -63,6 +165,30 @@ mod tests {
     assert_eq!(documents[0].page_content, expected);
   }
 
+  #[test]
+  fn test_html_metadata() {
+    let metadata = html_metadata(
+      r#"<!doctype html><html lang="fr"><head>
+        <title>
+          Notes &amp; Queries
+        </title>
+        <meta name="author" content="Jane Doe">
+        <meta property="og:title" content="Ignored">
+        <meta name=keywords content='notes, queries'/>
+        <meta property="article:published_time" content="2024-03-05T12:00:00+00:00">
+      </head><body><meta name="description" content="Not in the head"></body></html>"#,
+    );
+    assert_eq!(metadata.title.as_deref(), Some("Notes & Queries"));
+    assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+    assert_eq!(metadata.keywords.as_deref(), Some("notes, queries"));
+    assert_eq!(metadata.language.as_deref(), Some("fr"));
+    assert_eq!(metadata.created.as_deref(), Some("2024-03-05T12:00:00Z"));
+    assert_eq!(metadata.subject, None);
+
+    let metadata = html_metadata(include_str!("../../../fixtures/sample.html"));
+    assert_eq!(metadata.title.as_deref(), Some("Example Domain"));
+  }
+
   #[test]
   fn test_html_load_from_path() {
     let buffer = include_bytes!("../../../fixtures/sample.html");
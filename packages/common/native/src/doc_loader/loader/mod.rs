@@ -1,7 +1,22 @@
+mod archive;
+mod blocks;
+mod deadline;
 mod docx;
+mod email;
+mod encoding;
+mod epub;
 mod html;
+mod notebook;
+mod odt;
+mod package;
 mod pdf;
+mod presentation;
+mod properties;
+mod rtf;
 mod source;
+mod spreadsheet;
+mod structured;
+mod subtitle;
 mod text;
 
 use std::io::{Read, Seek};
@@ -20,9 +35,22 @@ pub trait Loader: Send + Sync {
   }
 }
 
+pub use archive::{Archive, ArchiveBudget};
+pub use deadline::Deadline;
 pub use docx::DocxLoader;
-pub use html::HtmlLoader;
+pub use email::EmailLoader;
+pub use encoding::decode_text;
+pub use epub::EpubLoader;
+pub use html::{html_metadata, HtmlLoader};
+pub use notebook::NotebookLoader;
+pub use odt::OdtLoader;
 pub use pdf::PdfExtractLoader;
+pub use presentation::PresentationLoader;
+pub use properties::{iso_639_1, normalize_date, normalize_language, read_properties};
+pub use rtf::RtfLoader;
 pub use source::{get_language_by_filename, LanguageParserOptions, SourceCodeLoader};
+pub use spreadsheet::SpreadsheetLoader;
+pub use structured::{DataFormat, StructuredLoader};
+pub use subtitle::SubtitleLoader;
 pub use text::TextLoader;
 pub use url::Url;
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{
+  source::{get_language_by_filename, get_language_by_name, LanguageParser},
+  *,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CellType {
+  Markdown,
+  Code,
+  Raw,
+}
+
+impl CellType {
+  fn as_str(&self) -> &'static str {
+    match self {
+      CellType::Markdown => "markdown",
+      CellType::Code => "code",
+      CellType::Raw => "raw",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+  cell_type: CellType,
+  source: String,
+  outputs: Vec<String>,
+}
+
+/// Loads Jupyter notebooks, markdown cells are split like markdown documents
+/// and code cells are parsed in the language of the notebook's kernel. The
+/// text outputs of code cells are kept in the `outputs` metadata.
+#[derive(Debug, Clone)]
+pub struct NotebookLoader {
+  cells: Vec<Cell>,
+  parser_option: Option<LanguageParserOptions>,
+}
+
+/// Multiline strings of notebooks are either a string or a list of lines.
+fn multiline(value: &Value) -> String {
+  match value {
+    Value::String(text) => text.clone(),
+    Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+    _ => String::new(),
+  }
+}
+
+fn read_outputs(cell: &Value) -> Vec<String> {
+  let Some(outputs) = cell["outputs"].as_array() else {
+    return vec![];
+  };
+  outputs
+    .iter()
+    .map(|output| match output["output_type"].as_str() {
+      Some("stream") => multiline(&output["text"]),
+      Some("execute_result" | "display_data") => multiline(&output["data"]["text/plain"]),
+      Some("error") => format!(
+        "{}: {}",
+        output["ename"].as_str().unwrap_or_default(),
+        output["evalue"].as_str().unwrap_or_default()
+      ),
+      _ => String::new(),
+    })
+    .map(|text| text.trim_end().to_string())
+    .filter(|text| !text.is_empty())
+    .collect()
+}
+
+impl NotebookLoader {
+  pub fn new(json: &str) -> LoaderResult<Self> {
+    let notebook: Value = serde_json::from_str(json)?;
+    let cells = notebook["cells"]
+      .as_array()
+      .ok_or(LoaderError::Other("Invalid notebook".into()))?
+      .iter()
+      .filter_map(|cell| {
+        let cell_type = match cell["cell_type"].as_str()? {
+          "markdown" => CellType::Markdown,
+          "code" => CellType::Code,
+          _ => CellType::Raw,
+        };
+        Some(Cell {
+          cell_type,
+          source: multiline(&cell["source"]),
+          outputs: read_outputs(cell),
+        })
+      })
+      .collect();
+
+    let metadata = &notebook["metadata"];
+    let language = metadata["language_info"]["file_extension"]
+      .as_str()
+      .and_then(|extension| get_language_by_filename(extension).ok())
+      .or_else(|| {
+        [
+          &metadata["kernelspec"]["language"],
+          &metadata["language_info"]["name"],
+        ]
+        .into_iter()
+        .find_map(|name| get_language_by_name(name.as_str()?).ok())
+      });
+
+    Ok(Self {
+      cells,
+      parser_option: language.map(|language| LanguageParserOptions {
+        language,
+        ..Default::default()
+      }),
+    })
+  }
+
+  /// Code cells spanning fewer lines are kept whole instead of being split
+  /// into functions and classes.
+  pub fn with_parser_threshold(mut self, threshold: u64) -> Self {
+    if let Some(options) = &mut self.parser_option {
+      options.parser_threshold = threshold;
+    }
+    self
+  }
+}
+
+fn is_code(document: &Document) -> bool {
+  document.metadata.get("cell_type") == Some(&Value::from(CellType::Code.as_str()))
+}
+
+impl Loader for NotebookLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let mut documents = vec![];
+    let mut offset = 0;
+    for (index, cell) in self.cells.into_iter().enumerate() {
+      if cell.source.trim().is_empty() {
+        offset += cell.source.len();
+        continue;
+      }
+      let mut metadata = HashMap::from([
+        ("cell_index".to_string(), Value::from(index)),
+        (
+          "cell_type".to_string(),
+          Value::from(cell.cell_type.as_str()),
+        ),
+      ]);
+      if cell.cell_type == CellType::Code {
+        metadata.insert("outputs".to_string(), Value::from(cell.outputs));
+      }
+
+      let docs = match &self.parser_option {
+        Some(options) if cell.cell_type == CellType::Code => {
+          LanguageParser::from_language(options.language.clone())
+            .with_parser_threshold(options.parser_threshold)
+            .with_class_threshold(options.class_threshold)
+            .parse_code(&cell.source)?
+        }
+        _ => vec![Document::new(&cell.source)],
+      };
+      for mut doc in docs {
+        // cells are laid out one after another in the extracted text, chunks
+        // of code cells carry their range in the cell
+        for key in ["start", "end"] {
+          let position = doc.metadata.get(key).and_then(Value::as_u64);
+          if let Some(position) = position.or((key == "start").then_some(0)) {
+            doc
+              .metadata
+              .insert(key.to_string(), Value::from(offset + position as usize));
+          }
+        }
+        doc.metadata.extend(metadata.clone());
+        documents.push(doc);
+      }
+      offset += cell.source.len();
+    }
+    Ok(documents)
+  }
+
+  /// Markdown and raw cells go through the splitter, code cells are already
+  /// chunked by the language parser.
+  fn load_and_split<TS: TextSplitter + 'static>(self, splitter: TS) -> LoaderResult<Vec<Document>>
+  where
+    Self: Sized,
+  {
+    let mut documents = vec![];
+    for mut doc in self.load()? {
+      if !is_code(&doc) {
+        documents.extend(splitter.split_documents(&[doc])?);
+        continue;
+      }
+      if !doc.metadata.contains_key("end") {
+        if let Some(start) = doc.metadata.get("start").and_then(Value::as_u64) {
+          let end = start as usize + doc.page_content.len();
+          doc.metadata.insert("end".into(), Value::from(end));
+        }
+      }
+      documents.push(doc);
+    }
+    Ok(documents)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_notebook() {
+    let json = include_str!("../../../fixtures/sample.ipynb");
+    let documents = NotebookLoader::new(json)
+      .unwrap()
+      .with_parser_threshold(0)
+      .load()
+      .unwrap();
+
+    let source = NotebookLoader::new(json)
+      .unwrap()
+      .cells
+      .into_iter()
+      .map(|cell| cell.source)
+      .collect::<String>();
+    for doc in &documents {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      assert_eq!(
+        &source[start..start + doc.page_content.len()],
+        doc.page_content
+      );
+    }
+
+    assert_eq!(documents[0].metadata["cell_index"], 0);
+    assert_eq!(documents[0].metadata["cell_type"], "markdown");
+    assert!(!documents[0].metadata.contains_key("outputs"));
+
+    let code = documents
+      .iter()
+      .filter(|doc| doc.metadata["cell_index"] == 1)
+      .collect::<Vec<_>>();
+    assert!(code.iter().any(|doc| doc.metadata["symbol"] == "mean"));
+    assert!(code.iter().all(|doc| doc.metadata["language"] == "Python"));
+    assert!(code
+      .iter()
+      .all(|doc| doc.metadata["outputs"] == Value::from(vec!["loaded 3 rows", "2.0"])));
+
+    let error = documents
+      .iter()
+      .find(|doc| doc.metadata["cell_index"] == 3)
+      .unwrap();
+    assert_eq!(
+      error.metadata["outputs"],
+      Value::from(vec!["ZeroDivisionError: division by zero"])
+    );
+  }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use quick_xml::{
+  events::{BytesStart, Event},
+  Reader,
+};
+use zip::{result::ZipError, ZipArchive};
+
+use super::*;
+
+/// Bytes read from the files of a package at most, which like the entries of
+/// archives may expand far beyond the size of the package.
+const MAX_PACKAGE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// The zip package OOXML and OpenDocument files are stored in.
+pub struct Package<R> {
+  archive: ZipArchive<R>,
+  budget: ArchiveBudget,
+}
+
+impl<R: Read + Seek> Package<R> {
+  pub fn new(reader: R) -> LoaderResult<Self> {
+    Ok(Self {
+      archive: ZipArchive::new(reader)?,
+      budget: ArchiveBudget::new(usize::MAX, MAX_PACKAGE_SIZE),
+    })
+  }
+
+  /// Limits the bytes read from the files of the package, reading more fails
+  /// with `LoaderError::ArchiveTooLarge`.
+  pub fn with_max_size(mut self, max_size: u64) -> Self {
+    self.budget = ArchiveBudget::new(usize::MAX, max_size);
+    self
+  }
+
+  /// Reads every file of the package, failing like [`Package::read`] once
+  /// they expand to more than the size limit.
+  pub fn check_size(&mut self) -> LoaderResult<()> {
+    for i in 0..self.archive.len() {
+      self.budget.read(self.archive.by_index(i)?)?;
+    }
+    Ok(())
+  }
+
+  pub fn contains(&self, name: &str) -> bool {
+    self.archive.index_for_name(name).is_some()
+  }
+
+  /// Reads a file of the package, `None` if it does not exist.
+  pub fn read(&mut self, name: &str) -> LoaderResult<Option<String>> {
+    let file = match self.archive.by_name(name) {
+      Ok(file) => file,
+      Err(ZipError::FileNotFound) => return Ok(None),
+      Err(e) => return Err(e.into()),
+    };
+    let content = self.budget.read(file)?;
+    Ok(Some(String::from_utf8(content)?))
+  }
+
+  /// Reads the relationships of an OOXML part, as id -> (type, resolved
+  /// target path).
+  pub fn relationships(&mut self, part: &str) -> LoaderResult<HashMap<String, (String, String)>> {
+    let (dir, file) = part.rsplit_once('/').unwrap_or(("", part));
+    let rels_path = if dir.is_empty() {
+      format!("_rels/{}.rels", file)
+    } else {
+      format!("{}/_rels/{}.rels", dir, file)
+    };
+    let Some(rels) = self.read(&rels_path)? else {
+      return Ok(HashMap::new());
+    };
+
+    let mut relationships = HashMap::new();
+    let mut reader = Reader::from_str(&rels);
+    loop {
+      match reader.read_event()? {
+        Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+          let attr = |name| attribute(&e, name).map(Option::unwrap_or_default);
+          relationships.insert(
+            attr("Id")?,
+            (attr("Type")?, resolve_path(dir, &attr("Target")?)),
+          );
+        }
+        Event::Eof => break,
+        _ => {}
+      }
+    }
+    Ok(relationships)
+  }
+}
+
+/// Reads the unescaped value of an attribute of an xml element.
+pub fn attribute(element: &BytesStart, name: &str) -> LoaderResult<Option<String>> {
+  Ok(
+    element
+      .try_get_attribute(name)
+      .map_err(quick_xml::Error::from)?
+      .map(|a| a.unescape_value().map(|v| v.to_string()))
+      .transpose()?,
+  )
+}
+
+/// Resolves a path relative to the directory `dir` of the package.
+pub fn resolve_path(dir: &str, target: &str) -> String {
+  let mut segments: Vec<&str> = match target.strip_prefix('/') {
+    Some(_) => vec![],
+    None => dir.split('/').filter(|s| !s.is_empty()).collect(),
+  };
+  for segment in target.split('/') {
+    match segment {
+      "" | "." => {}
+      ".." => {
+        segments.pop();
+      }
+      segment => segments.push(segment),
+    }
+  }
+  segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Cursor, Write};
+
+  use zip::{write::SimpleFileOptions, ZipWriter};
+
+  use super::*;
+
+  #[test]
+  fn test_resolve_path() {
+    assert_eq!(
+      resolve_path("ppt", "slides/slide1.xml"),
+      "ppt/slides/slide1.xml"
+    );
+    assert_eq!(
+      resolve_path("ppt/slides", "../notesSlides/notesSlide1.xml"),
+      "ppt/notesSlides/notesSlide1.xml"
+    );
+    assert_eq!(resolve_path("ppt", "/ppt/media/a.png"), "ppt/media/a.png");
+  }
+
+  #[test]
+  fn test_read_limit() {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    for name in ["a.xml", "b.xml"] {
+      writer
+        .start_file(name, SimpleFileOptions::default())
+        .unwrap();
+      writer.write_all(&[b'a'; 1000]).unwrap();
+    }
+    let buffer = writer.finish().unwrap().into_inner();
+
+    // the files read before count against the limit
+    let mut package = Package::new(Cursor::new(buffer))
+      .unwrap()
+      .with_max_size(1500);
+    assert_eq!(package.read("a.xml").unwrap().unwrap().len(), 1000);
+    assert_eq!(package.read("c.xml").unwrap(), None);
+    assert!(matches!(
+      package.read("b.xml"),
+      Err(LoaderError::ArchiveTooLarge(1500))
+    ));
+  }
+}
@@ -1,4 +1,13 @@
-use pdf_extract::{output_doc, output_doc_encrypted, PlainTextOutput};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+};
+
+use pdf_extract::{encryption::DecryptionError, output_doc_page, PlainTextOutput};
+use serde_json::Value;
 
 /**
  * modified from https://github.com/Abraxas-365/langchain-rust/tree/v4.6.0/src/document_loaders
@@ -7,37 +16,413 @@ use super::*;
 
 #[derive(Debug, Clone)]
 pub struct PdfExtractLoader {
+  /// The document, decrypted once a password opened it
   document: pdf_extract::Document,
+  /// Whether the document is encrypted and still waits for its password
+  locked: bool,
+  split_pages: bool,
+  strip_headers: bool,
+  deadline: Option<Deadline>,
+  abort: Option<Arc<AtomicBool>>,
 }
 
 impl PdfExtractLoader {
   pub fn new<R: Read>(reader: R) -> Result<Self, LoaderError> {
     let document = pdf_extract::Document::load_from(reader)?;
-    Ok(Self { document })
+    let mut loader = Self {
+      locked: document.is_encrypted(),
+      document,
+      split_pages: false,
+      strip_headers: true,
+      deadline: None,
+      abort: None,
+    };
+    // documents that only restrict permissions are encrypted with an empty
+    // user password, so try that one before any password is provided
+    match loader.decrypt("") {
+      Ok(()) | Err(LoaderError::IncorrectPassword) => Ok(loader),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Decrypts the document with `password` if it is still encrypted, failing
+  /// with [`LoaderError::IncorrectPassword`] when it does not open it.
+  pub fn with_password<S: Into<String>>(mut self, password: S) -> LoaderResult<Self> {
+    let password = password.into();
+    if !password.is_empty() {
+      self.decrypt(&password)?;
+    }
+    Ok(self)
+  }
+
+  /// Emit one document per page, tagged with its `page_number`, instead of a
+  /// single document for the whole file.
+  pub fn with_split_pages(mut self, split_pages: bool) -> Self {
+    self.split_pages = split_pages;
+    self
+  }
+
+  /// Remove running headers, footers and page numbers, which are the lines
+  /// repeated at the same position from the top or the bottom of the pages.
+  /// Enabled by default.
+  pub fn with_strip_headers(mut self, strip_headers: bool) -> Self {
+    self.strip_headers = strip_headers;
+    self
+  }
+
+  /// Fail with a timeout once `deadline` passed, which is checked before
+  /// every page.
+  pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  /// Fail with [`LoaderError::Aborted`] once `abort` is set, which is checked
+  /// before every page like the deadline.
+  pub fn with_abort(mut self, abort: Arc<AtomicBool>) -> Self {
+    self.abort = Some(abort);
+    self
   }
 }
 
+/// Number of lines from the top and the bottom of pages checked for headers
+/// and footers
+const RUNNING_LINES: usize = 3;
+
+/// Number of characters above which a line is taken for body text rather than
+/// a header or footer
+const MAX_RUNNING_LINE_LEN: usize = 80;
+
+/// Returns the line with its numbers replaced, so that page numbers and
+/// dates repeat across pages.
+fn normalize_line(line: &str) -> String {
+  let mut normalized = String::new();
+  for word in line.split_whitespace() {
+    if !normalized.is_empty() {
+      normalized.push(' ');
+    }
+    for c in word.chars() {
+      if !c.is_ascii_digit() {
+        normalized.push(c);
+      } else if !normalized.ends_with('#') {
+        normalized.push('#');
+      }
+    }
+  }
+  normalized
+}
+
+/// Returns the positions of the non-empty lines of `text` near its top or
+/// bottom, counted from the top as 0, 1, ... and from the bottom as -1, -2,
+/// ..., with the number of the line and its normalized text.
+fn positions(text: &str) -> Vec<(usize, isize, String)> {
+  let lines = text
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(number, line)| (number, normalize_line(line)))
+    .collect::<Vec<_>>();
+  let len = lines.len();
+  lines
+    .into_iter()
+    .enumerate()
+    .flat_map(move |(i, (number, line))| {
+      let mut positions = vec![];
+      if i < RUNNING_LINES {
+        positions.push((number, i as isize, line.clone()));
+      }
+      if len - i <= RUNNING_LINES {
+        positions.push((number, i as isize - len as isize, line));
+      }
+      positions
+    })
+    .collect()
+}
+
+/// Counts the lines found at the same position from the top or the bottom
+/// of the pages of a document.
+#[derive(Default)]
+struct RunningLines {
+  counts: HashMap<(isize, String), usize>,
+  pages: usize,
+}
+
+impl RunningLines {
+  fn add(&mut self, text: &str) {
+    self.pages += 1;
+    let keys = positions(text)
+      .into_iter()
+      .map(|(_, position, line)| (position, line))
+      .collect::<HashSet<_>>();
+    for key in keys {
+      *self.counts.entry(key).or_default() += 1;
+    }
+  }
+
+  /// Number of pages a line must be found in to be a running line, more than
+  /// half of the pages and all of them in documents of up to three pages.
+  fn min_count(&self) -> usize {
+    (self.pages / 2 + 1).max(self.pages.min(3))
+  }
+
+  /// Removes the short lines of `text` found at the same position in enough
+  /// pages. Headers and footers start at the edges of the page, so the lines
+  /// following a line of the body are kept.
+  fn strip(&self, text: &mut String) {
+    if self.pages < 2 {
+      return;
+    }
+    let min_count = self.min_count();
+    let is_running = |(_, position, line): &&(usize, isize, String)| {
+      line.chars().count() <= MAX_RUNNING_LINE_LEN
+        && self
+          .counts
+          .get(&(*position, line.clone()))
+          .is_some_and(|count| *count >= min_count)
+    };
+    let positions = positions(text);
+    let headers = positions
+      .iter()
+      .filter(|(_, position, _)| *position >= 0)
+      .take_while(is_running);
+    let footers = positions
+      .iter()
+      .filter(|(_, position, _)| *position < 0)
+      .rev()
+      .take_while(is_running);
+    let running = headers
+      .chain(footers)
+      .map(|(number, _, _)| *number)
+      .collect::<HashSet<_>>();
+    if running.is_empty() {
+      return;
+    }
+    *text = text
+      .split_inclusive('\n')
+      .enumerate()
+      .filter(|(number, _)| !running.contains(number))
+      .map(|(_, line)| line)
+      .collect();
+  }
+}
+
+/// Removes the running headers, footers and page numbers of the pages.
+fn strip_running_lines(pages: &mut [(u32, String)]) {
+  let mut running = RunningLines::default();
+  for (_, text) in pages.iter() {
+    running.add(text);
+  }
+  for (_, text) in pages.iter_mut() {
+    running.strip(text);
+  }
+}
+
+fn page_text(doc: &pdf_extract::Document, page_number: u32) -> LoaderResult<String> {
+  let mut buffer: Vec<u8> = Vec::new();
+  let mut output = PlainTextOutput::new(&mut buffer as &mut dyn std::io::Write);
+  output_doc_page(doc, &mut output, page_number)?;
+  Ok(String::from_utf8(buffer)?)
+}
+
+/// Returns the document of a page whose text starts at `start` in the text
+/// of the pages laid out one after another.
+fn page_document(page_number: u32, start: usize, text: String) -> Document {
+  Document::new(text).with_metadata(HashMap::from([
+    ("page_number".to_string(), Value::from(page_number)),
+    ("start".to_string(), Value::from(start)),
+  ]))
+}
+
 impl PdfExtractLoader {
-  fn extract_text(&self) -> Result<String, LoaderError> {
+  /// Decrypts the document once, keeping the encrypted one until `password`
+  /// opens it.
+  fn decrypt(&mut self, password: &str) -> LoaderResult<()> {
+    if !self.locked {
+      return Ok(());
+    }
     let mut doc = self.document.clone();
-    let mut buffer: Vec<u8> = Vec::new();
-    let mut output = PlainTextOutput::new(&mut buffer as &mut dyn std::io::Write);
-    if doc.is_encrypted() {
-      output_doc_encrypted(&mut doc, &mut output, "")?;
-    } else {
-      output_doc(&doc, &mut output)?;
+    doc.decrypt(password).map_err(|e| match e {
+      pdf_extract::Error::Decryption(DecryptionError::IncorrectPassword) => {
+        LoaderError::IncorrectPassword
+      }
+      e => e.into(),
+    })?;
+    self.document = doc;
+    self.locked = false;
+    Ok(())
+  }
+
+  fn document(&self) -> LoaderResult<&pdf_extract::Document> {
+    if self.locked {
+      return Err(LoaderError::PasswordRequired);
     }
-    Ok(String::from_utf8(buffer)?)
+    Ok(&self.document)
+  }
+
+  /// Reads the title, author and dates of the document from its XMP
+  /// metadata, falling back to the Info dictionary of the trailer.
+  pub fn metadata(&self) -> LoaderResult<DocMetadata> {
+    let doc = self.document()?;
+    let catalog = doc.catalog().ok();
+    let mut metadata = catalog
+      .and_then(|catalog| catalog.get(b"Metadata").ok())
+      .and_then(|xmp| xmp.as_reference().ok())
+      .and_then(|id| doc.get_object(id).ok())
+      .and_then(|xmp| xmp.as_stream().ok())
+      .and_then(|xmp| {
+        let content = xmp
+          .decompressed_content()
+          .unwrap_or_else(|_| xmp.content.clone());
+        read_properties(&String::from_utf8_lossy(&content)).ok()
+      })
+      .unwrap_or_default();
+
+    let info = doc
+      .trailer
+      .get(b"Info")
+      .ok()
+      .and_then(|info| doc.dereference(info).ok())
+      .and_then(|(_, info)| info.as_dict().ok());
+    if let Some(info) = info {
+      let text = |key: &[u8]| {
+        info
+          .get(key)
+          .ok()
+          .and_then(|value| value.as_str().ok())
+          .map(decode_text_string)
+          .filter(|value| !value.trim().is_empty())
+      };
+      metadata.title = metadata.title.or_else(|| text(b"Title"));
+      metadata.author = metadata.author.or_else(|| text(b"Author"));
+      metadata.subject = metadata.subject.or_else(|| text(b"Subject"));
+      metadata.keywords = metadata.keywords.or_else(|| text(b"Keywords"));
+      metadata.created = metadata
+        .created
+        .or_else(|| text(b"CreationDate").map(|date| normalize_date(&date)));
+      metadata.modified = metadata
+        .modified
+        .or_else(|| text(b"ModDate").map(|date| normalize_date(&date)));
+    }
+    metadata.language = metadata.language.or_else(|| {
+      catalog
+        .and_then(|catalog| catalog.get(b"Lang").ok())
+        .and_then(|lang| lang.as_str().ok())
+        .map(decode_text_string)
+    });
+    Ok(metadata)
+  }
+
+  fn extract_page(&self, doc: &pdf_extract::Document, page_number: u32) -> LoaderResult<String> {
+    if let Some(deadline) = &self.deadline {
+      deadline.check()?;
+    }
+    if let Some(abort) = &self.abort {
+      if abort.load(Ordering::Relaxed) {
+        return Err(LoaderError::Aborted);
+      }
+    }
+    page_text(doc, page_number)
+  }
+
+  /// Returns the text of the pages one after another, which is the text the
+  /// offsets of the pages point into.
+  fn extract_text(&self) -> Result<String, LoaderError> {
+    Ok(
+      self
+        .extract_pages()?
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect(),
+    )
+  }
+
+  fn extract_pages(&self) -> Result<Vec<(u32, String)>, LoaderError> {
+    let doc = self.document()?;
+    let mut pages = doc
+      .get_pages()
+      .into_keys()
+      .map(|page_number| Ok((page_number, self.extract_page(doc, page_number)?)))
+      .collect::<Result<Vec<_>, LoaderError>>()?;
+    if self.strip_headers {
+      strip_running_lines(&mut pages);
+    }
+    Ok(pages)
   }
 
   fn extract_text_to_doc(&self) -> Result<Document, LoaderError> {
     let text = self.extract_text()?;
     Ok(Document::new(text))
   }
+
+  fn extract_pages_to_docs(&self) -> Result<Vec<Document>, LoaderError> {
+    let mut offset = 0;
+    Ok(
+      self
+        .extract_pages()?
+        .into_iter()
+        .map(|(page_number, text)| {
+          // pages are laid out one after another in the extracted text
+          let start = offset;
+          offset += text.len();
+          page_document(page_number, start, text)
+        })
+        .collect(),
+    )
+  }
+
+  /// Hands the pages to `on_page` one after another, as the documents of
+  /// [`PdfExtractLoader::with_split_pages`], until it returns `false`.
+  ///
+  /// Only one page is held in memory at a time. Running lines are counted in
+  /// a first pass over the pages, which are extracted again afterwards.
+  pub fn stream_pages(
+    &self,
+    mut on_page: impl FnMut(Document) -> LoaderResult<bool>,
+  ) -> LoaderResult<()> {
+    let doc = self.document()?;
+    let page_numbers = doc.get_pages().into_keys().collect::<Vec<_>>();
+    let mut running = RunningLines::default();
+    if self.strip_headers {
+      for page_number in &page_numbers {
+        running.add(&self.extract_page(doc, *page_number)?);
+      }
+    }
+    let mut offset = 0;
+    for page_number in page_numbers {
+      let mut text = self.extract_page(doc, page_number)?;
+      running.strip(&mut text);
+      let start = offset;
+      offset += text.len();
+      if !on_page(page_document(page_number, start, text))? {
+        break;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Decodes a PDF text string, which is UTF-16BE or UTF-8 with a byte order
+/// mark, or otherwise PDFDocEncoding, read as Latin-1 here.
+fn decode_text_string(bytes: &[u8]) -> String {
+  if let Some(utf16) = bytes.strip_prefix(&[0xfe, 0xff]) {
+    String::from_utf16_lossy(
+      &utf16
+        .chunks_exact(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect::<Vec<_>>(),
+    )
+  } else if let Some(utf8) = bytes.strip_prefix(&[0xef, 0xbb, 0xbf]) {
+    String::from_utf8_lossy(utf8).into_owned()
+  } else {
+    bytes.iter().map(|&b| b as char).collect()
+  }
 }
 
 impl Loader for PdfExtractLoader {
   fn load(self) -> LoaderResult<Vec<Document>> {
+    if self.split_pages {
+      return self.extract_pages_to_docs();
+    }
     let doc = self.extract_text_to_doc()?;
     Ok(vec![doc])
   }
@@ -49,10 +434,14 @@ mod tests {
 
   use super::*;
 
+  fn get_fixture(name: &str) -> Vec<u8> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    read(fixtures.join(name)).unwrap()
+  }
+
   #[test]
   fn test_parse_pdf() {
-    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
-    let buffer = read(fixtures.join("sample.pdf")).unwrap();
+    let buffer = get_fixture("sample.pdf");
 
     let reader = Cursor::new(buffer);
     let loader = PdfExtractLoader::new(reader).expect("Failed to create PdfExtractLoader");
@@ -66,4 +455,167 @@ mod tests {
        consectetuer  a"
     );
   }
+
+  #[test]
+  fn test_parse_pdf_pages() {
+    for fixture in ["sample.pdf", "report.pdf"] {
+      let buffer = get_fixture(fixture);
+      for strip_headers in [true, false] {
+        let loader = PdfExtractLoader::new(Cursor::new(&buffer))
+          .unwrap()
+          .with_strip_headers(strip_headers);
+        let text = loader.load().unwrap().remove(0).page_content;
+
+        let loader = PdfExtractLoader::new(Cursor::new(&buffer))
+          .unwrap()
+          .with_split_pages(true)
+          .with_strip_headers(strip_headers);
+        let pages = loader.load().unwrap();
+        let mut end = 0;
+        for (index, page) in pages.iter().enumerate() {
+          assert_eq!(page.metadata["page_number"], Value::from(index + 1));
+          // pages follow each other without gaps
+          let start = page.metadata["start"].as_u64().unwrap() as usize;
+          assert_eq!(start, end);
+          end = start + page.page_content.len();
+          assert_eq!(&text[start..end], page.page_content);
+        }
+        assert_eq!(end, text.len());
+
+        let loader = PdfExtractLoader::new(Cursor::new(&buffer))
+          .unwrap()
+          .with_strip_headers(strip_headers);
+        let mut streamed = vec![];
+        loader
+          .stream_pages(|page| {
+            streamed.push(page);
+            Ok(true)
+          })
+          .unwrap();
+        assert_eq!(streamed.len(), pages.len());
+        for (streamed, page) in streamed.iter().zip(&pages) {
+          assert_eq!(streamed.page_content, page.page_content);
+          assert_eq!(streamed.metadata, page.metadata);
+        }
+
+        let loader = PdfExtractLoader::new(Cursor::new(&buffer))
+          .unwrap()
+          .with_split_pages(true)
+          .with_strip_headers(strip_headers);
+        let docs = loader.load_and_split(TokenSplitter::default()).unwrap();
+        assert_eq!(docs.len(), pages.len());
+        for doc in docs {
+          let start = doc.metadata["start"].as_u64().unwrap() as usize;
+          let end = doc.metadata["end"].as_u64().unwrap() as usize;
+          assert_eq!(&text[start..end], doc.page_content);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_strip_running_lines() {
+    let page = |number: u32, body: &str| {
+      (
+        number,
+        format!(
+          "\n\nACME Quarterly Report   2024\n\n{}\n\nConfidential\n- {} -\n",
+          body, number
+        ),
+      )
+    };
+    let mut pages = vec![
+      page(1, "Revenue grew in every region."),
+      page(2, "Costs were stable."),
+      page(3, "Page 3 opens with a number."),
+      page(4, "Outlook for the next year."),
+    ];
+    strip_running_lines(&mut pages);
+    for (number, text) in &pages {
+      assert!(!text.contains("ACME"), "{}", text);
+      assert!(!text.contains("Confidential"));
+      assert!(!text.contains(&format!("- {} -", number)));
+    }
+    assert_eq!(pages[2].1, "\n\n\nPage 3 opens with a number.\n\n");
+
+    // the header of a table repeated on two of four pages is part of the body
+    let mut pages = vec![
+      page(1, "Letter to shareholders."),
+      page(2, "Revenue by region\nRegion Revenue Costs\nTotal 1200 800"),
+      page(3, "Costs by quarter\nRegion Revenue Costs\nTotal 1200 800"),
+      page(4, "Outlook."),
+    ];
+    strip_running_lines(&mut pages);
+    for (_, text) in &pages[1..3] {
+      assert!(
+        text.contains("Region Revenue Costs\nTotal 1200 800"),
+        "{}",
+        text
+      );
+    }
+
+    // a single page has nothing to compare with
+    let mut pages = vec![page(1, "Alone.")];
+    strip_running_lines(&mut pages);
+    assert!(pages[0].1.contains("ACME"));
+  }
+
+  #[test]
+  fn test_strip_pdf_headers() {
+    let loader = PdfExtractLoader::new(Cursor::new(get_fixture("report.pdf")))
+      .unwrap()
+      .with_split_pages(true);
+    let pages = loader.load().unwrap();
+    assert_eq!(pages.len(), 4);
+    for (index, page) in pages.iter().enumerate() {
+      let text = &page.page_content;
+      assert!(!text.contains("ACME Corp. Annual Report 2024"), "{}", text);
+      assert!(!text.contains("Confidential"), "{}", text);
+      assert!(
+        !text.contains(&format!("Page {} of 4", index + 1)),
+        "{}",
+        text
+      );
+    }
+    // the table header and totals of pages 2 and 3 are body text
+    for page in &pages[1..3] {
+      assert!(page.page_content.contains("Region Revenue Costs"));
+      assert!(page.page_content.contains("Total 1200 800"));
+    }
+    assert!(pages[3].page_content.contains("Outlook"));
+  }
+
+  #[test]
+  fn test_decode_text_string() {
+    assert_eq!(decode_text_string(b"Report"), "Report");
+    assert_eq!(
+      decode_text_string(&[0xfe, 0xff, 0x00, 0x52, 0x65, 0xe5]),
+      "R\u{65e5}"
+    );
+    assert_eq!(decode_text_string(&[0x43, 0x61, 0x66, 0xe9]), "Café");
+  }
+
+  #[test]
+  fn test_parse_encrypted_pdf() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let buffer = read(fixtures.join("encrypted.pdf")).unwrap();
+    let load = |password: Option<&str>| {
+      let loader = PdfExtractLoader::new(Cursor::new(&buffer)).unwrap();
+      match password {
+        Some(password) => loader.with_password(password)?,
+        None => loader,
+      }
+      .load()
+    };
+
+    assert!(matches!(load(None), Err(LoaderError::PasswordRequired)));
+    assert!(matches!(
+      load(Some("wrong")),
+      Err(LoaderError::IncorrectPassword)
+    ));
+
+    let docs = load(Some("affine")).unwrap();
+    assert_eq!(docs.len(), 1);
+    assert!(docs[0].page_content.contains("Secret PDF content"));
+  }
 }
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use quick_xml::{events::Event, Reader};
+use serde_json::Value;
+
+use super::{
+  package::{attribute, Package},
+  *,
+};
+
+const SLIDE_RELATIONSHIP: &str =
+  "http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide";
+const NOTES_RELATIONSHIP: &str =
+  "http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesSlide";
+
+#[derive(Debug, Clone, Default)]
+struct Slide {
+  title: Option<String>,
+  body: Vec<String>,
+  notes: Vec<String>,
+}
+
+impl Slide {
+  fn to_markdown(&self) -> String {
+    let mut parts = vec![];
+    if let Some(title) = &self.title {
+      parts.push(format!("# {}", title));
+    }
+    if !self.body.is_empty() {
+      parts.push(self.body.join("\n"));
+    }
+    if !self.notes.is_empty() {
+      parts.push(format!("Notes:\n{}", self.notes.join("\n")));
+    }
+    parts.join("\n\n")
+  }
+}
+
+/// Loads PPTX and ODP presentations, one document per slide containing its
+/// title, body text and speaker notes.
+#[derive(Debug, Clone)]
+pub struct PresentationLoader {
+  slides: Vec<Slide>,
+}
+
+impl PresentationLoader {
+  pub fn new<R: Read + Seek>(reader: R) -> LoaderResult<Self> {
+    let mut package = Package::new(reader)?;
+    let slides = if package.contains("ppt/presentation.xml") {
+      read_pptx(&mut package)?
+    } else if let Some(content) = package.read("content.xml")? {
+      read_odp(&content)?
+    } else {
+      return Err(LoaderError::Other("Unsupported presentation format".into()));
+    };
+    Ok(Self { slides })
+  }
+}
+
+fn push_paragraph(paragraphs: &mut Vec<String>, paragraph: &mut String) {
+  let text = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+  if !text.is_empty() {
+    paragraphs.push(text);
+  }
+  paragraph.clear();
+}
+
+fn read_pptx<R: Read + Seek>(package: &mut Package<R>) -> LoaderResult<Vec<Slide>> {
+  let relationships = package.relationships("ppt/presentation.xml")?;
+  let presentation = package.read("ppt/presentation.xml")?.unwrap_or_default();
+
+  // slides are listed in presentation order by relationship id
+  let mut slide_paths = vec![];
+  let mut reader = Reader::from_str(&presentation);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sldId" => {
+        for attr in e.attributes() {
+          let attr = attr.map_err(quick_xml::Error::from)?;
+          if attr.key.local_name().as_ref() == b"id" && attr.key.prefix().is_some() {
+            if let Some((kind, path)) = relationships.get(attr.unescape_value()?.as_ref()) {
+              if kind == SLIDE_RELATIONSHIP {
+                slide_paths.push(path.clone());
+              }
+            }
+          }
+        }
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+
+  let mut slides = vec![];
+  for path in slide_paths {
+    let Some(xml) = package.read(&path)? else {
+      continue;
+    };
+    let mut slide = Slide::default();
+    for (placeholder, paragraphs) in read_pptx_shapes(&xml)? {
+      match placeholder.as_deref() {
+        Some("title" | "ctrTitle") => {
+          slide.title = Some(paragraphs.join(" ")).filter(|t| !t.is_empty())
+        }
+        Some("sldNum" | "dt" | "ftr" | "hdr") => {}
+        _ => slide.body.extend(paragraphs),
+      }
+    }
+
+    let notes = package
+      .relationships(&path)?
+      .into_values()
+      .find(|(kind, _)| kind == NOTES_RELATIONSHIP);
+    if let Some((_, notes_path)) = notes {
+      if let Some(xml) = package.read(&notes_path)? {
+        for (placeholder, paragraphs) in read_pptx_shapes(&xml)? {
+          if placeholder.as_deref() == Some("body") {
+            slide.notes.extend(paragraphs);
+          }
+        }
+      }
+    }
+    slides.push(slide);
+  }
+  Ok(slides)
+}
+
+/// Returns the placeholder type and the paragraphs of every shape of a slide.
+fn read_pptx_shapes(xml: &str) -> LoaderResult<Vec<(Option<String>, Vec<String>)>> {
+  let mut shapes = vec![];
+  let mut placeholder = None;
+  let mut paragraphs = vec![];
+  let mut paragraph = String::new();
+  let mut in_text = false;
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) if e.local_name().as_ref() == b"t" => in_text = true,
+      Event::End(e) if e.local_name().as_ref() == b"t" => in_text = false,
+      Event::Text(e) if in_text => paragraph.push_str(&e.unescape()?),
+      Event::Empty(e) if e.local_name().as_ref() == b"br" => paragraph.push(' '),
+      Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"ph" => {
+        // placeholders without a type are body placeholders
+        placeholder = Some(attribute(&e, "type")?.unwrap_or_else(|| "body".to_string()));
+      }
+      Event::End(e) if e.local_name().as_ref() == b"p" => {
+        push_paragraph(&mut paragraphs, &mut paragraph)
+      }
+      Event::End(e) if matches!(e.local_name().as_ref(), b"sp" | b"graphicFrame") => {
+        shapes.push((placeholder.take(), std::mem::take(&mut paragraphs)));
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok(shapes)
+}
+
+fn read_odp(content: &str) -> LoaderResult<Vec<Slide>> {
+  let mut slides = vec![];
+  let mut slide = Slide::default();
+  let mut class: Option<String> = None;
+  let mut in_notes = false;
+  let mut paragraph = String::new();
+  let mut reader = Reader::from_str(content);
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) => match e.name().as_ref() {
+        b"draw:page" => slide = Slide::default(),
+        b"presentation:notes" => in_notes = true,
+        b"draw:frame" => {
+          class = attribute(&e, "presentation:class")?;
+        }
+        _ => {}
+      },
+      Event::Empty(e) => match e.name().as_ref() {
+        b"text:s" | b"text:tab" | b"text:line-break" => paragraph.push(' '),
+        _ => {}
+      },
+      Event::Text(e) => paragraph.push_str(&e.unescape()?),
+      Event::End(e) => match e.name().as_ref() {
+        b"text:p" | b"text:h" => {
+          if in_notes {
+            push_paragraph(&mut slide.notes, &mut paragraph);
+          } else {
+            match class.as_deref() {
+              Some("title") => {
+                let mut title = slide.title.take().into_iter().collect();
+                push_paragraph(&mut title, &mut paragraph);
+                slide.title = Some(title.join(" ")).filter(|t| !t.is_empty());
+              }
+              Some("page-number" | "date-time" | "footer" | "header") => paragraph.clear(),
+              _ => push_paragraph(&mut slide.body, &mut paragraph),
+            }
+          }
+        }
+        b"draw:frame" => class = None,
+        b"presentation:notes" => in_notes = false,
+        b"draw:page" => slides.push(std::mem::take(&mut slide)),
+        _ => {}
+      },
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok(slides)
+}
+
+impl Loader for PresentationLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let mut offset = 0;
+    Ok(
+      self
+        .slides
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, slide)| {
+          let content = slide.to_markdown();
+          if content.is_empty() {
+            return None;
+          }
+          // slides are laid out one after another in the extracted text
+          let mut metadata = HashMap::from([
+            ("slide_number".to_string(), Value::from(i + 1)),
+            ("start".to_string(), Value::from(offset)),
+          ]);
+          offset += content.len();
+          if let Some(title) = slide.title {
+            metadata.insert("title".to_string(), Value::from(title));
+          }
+          Some(Document::new(content).with_metadata(metadata))
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use super::*;
+
+  fn assert_sample(documents: Vec<Document>) {
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].metadata["slide_number"], 1);
+    assert_eq!(documents[0].metadata["title"], "Quarterly Review");
+    assert_eq!(
+      documents[0].page_content,
+      "# Quarterly Review\n\nRevenue grew 12%\nChurn is down\n\nNotes:\nMention the new pricing"
+    );
+    assert_eq!(documents[1].metadata["slide_number"], 2);
+    assert_eq!(
+      documents[1].page_content,
+      "# Next Steps\n\nHire two engineers"
+    );
+  }
+
+  #[test]
+  fn test_parse_pptx() {
+    let buffer = include_bytes!("../../../fixtures/sample.pptx");
+    let loader = PresentationLoader::new(Cursor::new(buffer)).unwrap();
+    assert_sample(loader.load().unwrap());
+  }
+
+  #[test]
+  fn test_parse_odp() {
+    let buffer = include_bytes!("../../../fixtures/sample.odp");
+    let loader = PresentationLoader::new(Cursor::new(buffer)).unwrap();
+    assert_sample(loader.load().unwrap());
+  }
+}
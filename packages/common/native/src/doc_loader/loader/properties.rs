@@ -0,0 +1,295 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, SecondsFormat};
+use quick_xml::{events::Event, Reader};
+use whatlang::Lang;
+
+use super::*;
+
+/// Reads Dublin Core style properties, as found in the `docProps/core.xml` of
+/// OOXML files, the `meta.xml` of OpenDocument files and XMP packets. Values
+/// nested in `rdf:Alt` or `rdf:Seq` lists are attributed to the property
+/// enclosing them, the creators and keywords of a list are joined.
+pub fn read_properties(xml: &str) -> LoaderResult<DocMetadata> {
+  let mut metadata = DocMetadata::default();
+  let mut reader = Reader::from_str(xml);
+  let mut path: Vec<Vec<u8>> = vec![];
+  loop {
+    match reader.read_event()? {
+      Event::Start(e) => path.push(e.local_name().as_ref().to_vec()),
+      Event::End(_) => {
+        path.pop();
+      }
+      Event::Text(e) => {
+        let text = e.unescape()?;
+        let text = text.trim();
+        let Some(name) = path.iter().rev().find(|name| property(name).is_some()) else {
+          continue;
+        };
+        if text.is_empty() {
+          continue;
+        }
+        let (field, joined) = match property(name) {
+          Some(Property::Title) => (&mut metadata.title, false),
+          Some(Property::Author) => (&mut metadata.author, true),
+          Some(Property::Subject) => (&mut metadata.subject, false),
+          Some(Property::Keywords) => (&mut metadata.keywords, true),
+          Some(Property::Created) => (&mut metadata.created, false),
+          Some(Property::Modified) => (&mut metadata.modified, false),
+          Some(Property::Language) => (&mut metadata.language, false),
+          None => continue,
+        };
+        match field.as_mut() {
+          Some(value) if joined => value.push_str(&format!(", {}", text)),
+          Some(_) => {}
+          None => *field = Some(text.to_string()),
+        }
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+
+  metadata.created = metadata.created.map(|date| normalize_date(&date));
+  metadata.modified = metadata.modified.map(|date| normalize_date(&date));
+  Ok(metadata)
+}
+
+enum Property {
+  Title,
+  Author,
+  Subject,
+  Keywords,
+  Created,
+  Modified,
+  Language,
+}
+
+fn property(local_name: &[u8]) -> Option<Property> {
+  match local_name {
+    b"title" => Some(Property::Title),
+    b"creator" | b"initial-creator" => Some(Property::Author),
+    b"subject" | b"description" => Some(Property::Subject),
+    b"keywords" | b"keyword" | b"Keywords" => Some(Property::Keywords),
+    b"created" | b"creation-date" | b"CreateDate" => Some(Property::Created),
+    b"modified" | b"date" | b"ModifyDate" => Some(Property::Modified),
+    b"language" => Some(Property::Language),
+    _ => None,
+  }
+}
+
+/// Turns the dates of PDF files (`D:20240305120000+01'00'`) and the ISO 8601
+/// dates of other formats into RFC 3339, dates that do not parse are kept as
+/// they are.
+pub fn normalize_date(date: &str) -> String {
+  let date = date.trim();
+  let normalized = match date.strip_prefix("D:") {
+    Some(pdf_date) => parse_pdf_date(pdf_date),
+    None => DateTime::parse_from_rfc3339(date)
+      .ok()
+      .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+  };
+  normalized.unwrap_or_else(|| date.to_string())
+}
+
+/// Turns the language tags of documents (`en_us`, `EN-US`) into BCP 47 tags
+/// (`en-US`), three letter codes of languages that have a two letter ISO 639-1
+/// code are shortened to it. Tags that are no language tag return `None`.
+pub fn normalize_language(tag: &str) -> Option<String> {
+  let mut subtags = tag.trim().split(['-', '_']);
+  let primary = subtags.next()?.to_ascii_lowercase();
+  if !(2..=3).contains(&primary.len()) || !primary.bytes().all(|b| b.is_ascii_alphabetic()) {
+    return None;
+  }
+  let mut normalized = match Lang::from_code(primary.as_str()) {
+    Some(lang) => iso_639_1(lang).to_string(),
+    None => primary,
+  };
+  for subtag in subtags {
+    if subtag.is_empty() || subtag.len() > 8 || !subtag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+      return None;
+    }
+    normalized.push('-');
+    let alphabetic = subtag.bytes().all(|b| b.is_ascii_alphabetic());
+    match subtag.len() {
+      // regions are upper case and scripts title case
+      2 if alphabetic => normalized.push_str(&subtag.to_ascii_uppercase()),
+      4 if alphabetic => {
+        normalized.push_str(&subtag[..1].to_ascii_uppercase());
+        normalized.push_str(&subtag[1..].to_ascii_lowercase());
+      }
+      _ => normalized.push_str(&subtag.to_ascii_lowercase()),
+    }
+  }
+  Some(normalized)
+}
+
+/// ISO 639-1 code of a language whatlang detects.
+pub fn iso_639_1(lang: Lang) -> &'static str {
+  match lang {
+    Lang::Afr => "af",
+    Lang::Aka => "ak",
+    Lang::Amh => "am",
+    Lang::Ara => "ar",
+    Lang::Aze => "az",
+    Lang::Bel => "be",
+    Lang::Ben => "bn",
+    Lang::Bul => "bg",
+    Lang::Cat => "ca",
+    Lang::Ces => "cs",
+    Lang::Cmn => "zh",
+    Lang::Dan => "da",
+    Lang::Deu => "de",
+    Lang::Ell => "el",
+    Lang::Eng => "en",
+    Lang::Epo => "eo",
+    Lang::Est => "et",
+    Lang::Fin => "fi",
+    Lang::Fra => "fr",
+    Lang::Guj => "gu",
+    Lang::Heb => "he",
+    Lang::Hin => "hi",
+    Lang::Hrv => "hr",
+    Lang::Hun => "hu",
+    Lang::Hye => "hy",
+    Lang::Ind => "id",
+    Lang::Ita => "it",
+    Lang::Jav => "jv",
+    Lang::Jpn => "ja",
+    Lang::Kan => "kn",
+    Lang::Kat => "ka",
+    Lang::Khm => "km",
+    Lang::Kor => "ko",
+    Lang::Lat => "la",
+    Lang::Lav => "lv",
+    Lang::Lit => "lt",
+    Lang::Mal => "ml",
+    Lang::Mar => "mr",
+    Lang::Mkd => "mk",
+    Lang::Mya => "my",
+    Lang::Nep => "ne",
+    Lang::Nld => "nl",
+    Lang::Nob => "nb",
+    Lang::Ori => "or",
+    Lang::Pan => "pa",
+    Lang::Pes => "fa",
+    Lang::Pol => "pl",
+    Lang::Por => "pt",
+    Lang::Ron => "ro",
+    Lang::Rus => "ru",
+    Lang::Sin => "si",
+    Lang::Slk => "sk",
+    Lang::Slv => "sl",
+    Lang::Sna => "sn",
+    Lang::Spa => "es",
+    Lang::Srp => "sr",
+    Lang::Swe => "sv",
+    Lang::Tam => "ta",
+    Lang::Tel => "te",
+    Lang::Tgl => "tl",
+    Lang::Tha => "th",
+    Lang::Tuk => "tk",
+    Lang::Tur => "tr",
+    Lang::Ukr => "uk",
+    Lang::Urd => "ur",
+    Lang::Uzb => "uz",
+    Lang::Vie => "vi",
+    Lang::Yid => "yi",
+    Lang::Zul => "zu",
+  }
+}
+
+fn parse_pdf_date(date: &str) -> Option<String> {
+  let digits = date
+    .find(|c: char| !c.is_ascii_digit())
+    .unwrap_or(date.len());
+  let (fields, zone) = date.split_at(digits);
+  // only the year is required, the other fields default to their minimum
+  let field = |start: usize, default: u32| {
+    fields
+      .get(start..start + 2)
+      .map_or(Some(default), |field| field.parse().ok())
+  };
+  let datetime =
+    NaiveDate::from_ymd_opt(fields.get(..4)?.parse().ok()?, field(4, 1)?, field(6, 1)?)?
+      .and_hms_opt(field(8, 0)?, field(10, 0)?, field(12, 0)?)?;
+
+  let offset = match zone.chars().next() {
+    Some(sign @ ('+' | '-')) => {
+      let zone = zone[1..].replace('\'', "");
+      let hours: i32 = zone.get(..2)?.parse().ok()?;
+      let minutes: i32 = zone.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+      let seconds = (hours * 60 + minutes) * 60;
+      FixedOffset::east_opt(if sign == '-' { -seconds } else { seconds })?
+    }
+    _ => FixedOffset::east_opt(0)?,
+  };
+  Some(
+    datetime
+      .and_local_timezone(offset)
+      .single()?
+      .to_rfc3339_opts(SecondsFormat::Secs, true),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_properties() {
+    let core = r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/">
+      <dc:title>Quarterly report</dc:title>
+      <dc:creator>Jane Doe</dc:creator>
+      <cp:keywords>finance</cp:keywords>
+      <dc:language>en-US</dc:language>
+      <dcterms:created xsi:type="dcterms:W3CDTF">2024-03-05T12:00:00Z</dcterms:created>
+    </cp:coreProperties>"#;
+    let metadata = read_properties(core).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Quarterly report"));
+    assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+    assert_eq!(metadata.keywords.as_deref(), Some("finance"));
+    assert_eq!(metadata.language.as_deref(), Some("en-US"));
+    assert_eq!(metadata.created.as_deref(), Some("2024-03-05T12:00:00Z"));
+    assert_eq!(metadata.modified, None);
+
+    let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+      <rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+        <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Field notes</rdf:li></rdf:Alt></dc:title>
+        <dc:creator><rdf:Seq><rdf:li>Ann</rdf:li><rdf:li>Bob</rdf:li></rdf:Seq></dc:creator>
+        <xmp:ModifyDate>2024-03-06T08:30:00+01:00</xmp:ModifyDate>
+      </rdf:Description>
+    </rdf:RDF></x:xmpmeta>"#;
+    let metadata = read_properties(xmp).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Field notes"));
+    assert_eq!(metadata.author.as_deref(), Some("Ann, Bob"));
+    assert_eq!(
+      metadata.modified.as_deref(),
+      Some("2024-03-06T08:30:00+01:00")
+    );
+  }
+
+  #[test]
+  fn test_normalize_date() {
+    assert_eq!(
+      normalize_date("D:20240305120000+01'00'"),
+      "2024-03-05T12:00:00+01:00"
+    );
+    assert_eq!(normalize_date("D:20240305"), "2024-03-05T00:00:00Z");
+    assert_eq!(normalize_date("D:2024030512Z"), "2024-03-05T12:00:00Z");
+    assert_eq!(normalize_date("last tuesday"), "last tuesday");
+  }
+
+  #[test]
+  fn test_normalize_language() {
+    assert_eq!(normalize_language("en_us").as_deref(), Some("en-US"));
+    assert_eq!(
+      normalize_language(" ZH-hant-tw ").as_deref(),
+      Some("zh-Hant-TW")
+    );
+    assert_eq!(normalize_language("deu").as_deref(), Some("de"));
+    assert_eq!(normalize_language("es-419").as_deref(), Some("es-419"));
+    assert_eq!(normalize_language("haw").as_deref(), Some("haw"));
+    assert_eq!(normalize_language("English"), None);
+    assert_eq!(normalize_language("en-"), None);
+    assert_eq!(normalize_language(""), None);
+  }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use encoding_rs::{Encoding, BIG5, EUC_KR, GBK, MACINTOSH, SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+use super::{blocks::Blocks, spreadsheet::to_markdown_table, *};
+
+/// Destinations whose content is not part of the document text.
+const SKIPPED_DESTINATIONS: &[&str] = &[
+  "colortbl",
+  "datastore",
+  "fldinst",
+  "fonttbl",
+  "footer",
+  "footerf",
+  "footerl",
+  "footerr",
+  "footnote",
+  "header",
+  "headerf",
+  "headerl",
+  "headerr",
+  "info",
+  "latentstyles",
+  "listoverridetable",
+  "listtable",
+  "object",
+  "pict",
+  "revtbl",
+  "rsidtbl",
+  "themedata",
+  "xmlnstbl",
+];
+
+/// Returns the encoding of a Windows code page given by `\ansicpg`.
+fn code_page(code_page: i32) -> Option<&'static Encoding> {
+  match code_page {
+    874 | 1250..=1258 => Encoding::for_label(format!("windows-{}", code_page).as_bytes()),
+    932 => Some(SHIFT_JIS),
+    936 => Some(GBK),
+    949 => Some(EUC_KR),
+    950 => Some(BIG5),
+    10000 => Some(MACINTOSH),
+    65001 => Some(UTF_8),
+    _ => None,
+  }
+}
+
+enum Token<'a> {
+  Open,
+  Close,
+  Word(&'a str, Option<i32>),
+  Symbol(u8),
+  Hex(u8),
+  Text(&'a [u8]),
+}
+
+struct Tokenizer<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+  fn take_while(&mut self, predicate: impl Fn(u8) -> bool) -> &'a [u8] {
+    let start = self.pos;
+    while self.pos < self.bytes.len() && predicate(self.bytes[self.pos]) {
+      self.pos += 1;
+    }
+    &self.bytes[start..self.pos]
+  }
+
+  fn next_token(&mut self) -> Option<Token<'a>> {
+    let byte = *self.bytes.get(self.pos)?;
+    self.pos += 1;
+    Some(match byte {
+      b'{' => Token::Open,
+      b'}' => Token::Close,
+      b'\\' => {
+        let next = *self.bytes.get(self.pos)?;
+        if next.is_ascii_alphabetic() {
+          let word = self.take_while(|b| b.is_ascii_alphabetic());
+          let sign = if self.bytes.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+            -1
+          } else {
+            1
+          };
+          let digits = self.take_while(|b| b.is_ascii_digit());
+          let param = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|d| d.parse::<i32>().ok())
+            .map(|d| sign * d);
+          // a space delimiting the control word belongs to it
+          if self.bytes.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+          }
+          Token::Word(std::str::from_utf8(word).unwrap_or_default(), param)
+        } else if next == b'\'' {
+          let hex = self
+            .bytes
+            .get(self.pos + 1..self.pos + 3)
+            .unwrap_or_default();
+          self.pos = (self.pos + 3).min(self.bytes.len());
+          match std::str::from_utf8(hex).map(|h| u8::from_str_radix(h, 16)) {
+            Ok(Ok(byte)) => Token::Hex(byte),
+            _ => Token::Text(&[]),
+          }
+        } else {
+          self.pos += 1;
+          Token::Symbol(next)
+        }
+      }
+      b'\r' | b'\n' => Token::Text(&[]),
+      _ => {
+        self.pos -= 1;
+        Token::Text(self.take_while(|b| !matches!(b, b'{' | b'}' | b'\\' | b'\r' | b'\n')))
+      }
+    })
+  }
+
+  /// Skips the data of a `\bin` control word.
+  fn skip(&mut self, len: usize) {
+    self.pos = (self.pos + len).min(self.bytes.len());
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Destination {
+  Text,
+  Skip,
+  Stylesheet,
+  ListText,
+}
+
+/// The state that is saved on `{` and restored on `}`.
+#[derive(Clone, Copy)]
+struct Group {
+  destination: Destination,
+  /// number of fallback characters following a `\u` control word
+  uc: usize,
+  style: i32,
+  outline_level: Option<usize>,
+  in_table: bool,
+}
+
+impl Default for Group {
+  fn default() -> Self {
+    Self {
+      destination: Destination::Text,
+      uc: 1,
+      style: 0,
+      outline_level: None,
+      in_table: false,
+    }
+  }
+}
+
+#[derive(Default)]
+struct Style {
+  id: i32,
+  name: String,
+  outline_level: Option<usize>,
+}
+
+impl Style {
+  /// Heading level of the style, from its outline level or its name.
+  fn heading_level(&self) -> Option<usize> {
+    self.outline_level.map(|l| l + 1).or_else(|| {
+      let name = self.name.trim().trim_end_matches(';').to_lowercase();
+      name.strip_prefix("heading ")?.trim().parse().ok()
+    })
+  }
+}
+
+#[derive(Default)]
+struct Writer {
+  blocks: Blocks,
+  /// encoding of the code page set by `\ansicpg`, windows-1252 otherwise
+  encoding: Option<&'static Encoding>,
+  /// bytes of text that are decoded together, as characters of double byte
+  /// code pages may be written as two `\'hh` escapes
+  bytes: Vec<u8>,
+  paragraph: String,
+  list_item: bool,
+  cell: String,
+  row: Vec<String>,
+  rows: Vec<Vec<String>>,
+  headings: HashMap<i32, usize>,
+}
+
+impl Writer {
+  fn decode(&self, bytes: &[u8]) -> String {
+    let encoding = self.encoding.unwrap_or(WINDOWS_1252);
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+  }
+
+  fn flush_bytes(&mut self) {
+    if !self.bytes.is_empty() {
+      let bytes = std::mem::take(&mut self.bytes);
+      let text = self.decode(&bytes);
+      self.paragraph.push_str(&text);
+    }
+  }
+
+  fn flush_table(&mut self) {
+    if !self.row.is_empty() {
+      self.rows.push(std::mem::take(&mut self.row));
+    }
+    if let Some(table) = to_markdown_table(&std::mem::take(&mut self.rows)) {
+      self.blocks.push(table, false);
+    }
+  }
+
+  fn end_paragraph(&mut self, group: &Group) {
+    let list_item = std::mem::take(&mut self.list_item);
+    if group.in_table {
+      // paragraphs of a cell are kept on one line
+      let text = std::mem::take(&mut self.paragraph);
+      self.cell.push_str(text.trim());
+      self.cell.push(' ');
+      return;
+    }
+    self.flush_table();
+
+    let text = std::mem::take(&mut self.paragraph);
+    let text = text.trim();
+    if text.is_empty() {
+      return;
+    }
+    let level = group
+      .outline_level
+      .map(|l| l + 1)
+      .or_else(|| self.headings.get(&group.style).copied())
+      .filter(|level| *level > 0);
+    if let Some(level) = level {
+      let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+      let heading = format!("{} {}", "#".repeat(level.min(6)), text);
+      self.blocks.push(heading, false);
+    } else if list_item {
+      self.blocks.push(format!("- {}", text), true);
+    } else {
+      self.blocks.push(text.to_string(), false);
+    }
+  }
+
+  fn end_cell(&mut self) {
+    let text = std::mem::take(&mut self.paragraph);
+    self.cell.push_str(text.trim());
+    let cell = std::mem::take(&mut self.cell);
+    self.row.push(cell.trim().to_string());
+  }
+
+  fn end_row(&mut self) {
+    self.rows.push(std::mem::take(&mut self.row));
+  }
+}
+
+/// Converts an RTF document to markdown, paragraphs with a heading style or an
+/// outline level become headings.
+fn to_markdown(bytes: &[u8]) -> LoaderResult<String> {
+  if !bytes.starts_with(b"{\\rtf") {
+    return Err(LoaderError::Other("Invalid RTF document".into()));
+  }
+
+  let mut tokenizer = Tokenizer { bytes, pos: 0 };
+  let mut writer = Writer::default();
+  let mut stack: Vec<Group> = vec![];
+  let mut group = Group::default();
+  let mut style = Style::default();
+  let mut skip_chars = 0;
+  let mut surrogate: Option<u16> = None;
+
+  while let Some(token) = tokenizer.next_token() {
+    if !matches!(token, Token::Hex(_) | Token::Text(_)) {
+      writer.flush_bytes();
+    }
+    match token {
+      Token::Open => {
+        stack.push(group);
+        skip_chars = 0;
+      }
+      Token::Close => {
+        // closing the group of a style definition
+        if group.destination == Destination::Stylesheet {
+          let style = std::mem::take(&mut style);
+          if let Some(level) = style.heading_level() {
+            writer.headings.insert(style.id, level);
+          }
+        }
+        group = stack.pop().unwrap_or_default();
+        skip_chars = 0;
+      }
+      // optional destinations this loader does not know about
+      Token::Symbol(b'*') => group.destination = Destination::Skip,
+      // binary data may contain braces, even in skipped destinations
+      Token::Word("bin", Some(len)) => tokenizer.skip(len.max(0) as usize),
+      _ if group.destination == Destination::Skip => {}
+      Token::Word(word, param) if group.destination == Destination::Stylesheet => match word {
+        "s" => style.id = param.unwrap_or_default(),
+        "outlinelevel" => style.outline_level = param.map(|p| p.max(0) as usize),
+        _ => {}
+      },
+      Token::Text(text) if group.destination == Destination::Stylesheet => {
+        let name = writer.decode(text);
+        style.name.push_str(&name);
+      }
+      Token::Word(word, _) if SKIPPED_DESTINATIONS.contains(&word) => {
+        group.destination = Destination::Skip
+      }
+      Token::Word("stylesheet", _) => group.destination = Destination::Stylesheet,
+      Token::Word("listtext" | "pntext", _) => {
+        group.destination = Destination::ListText;
+        writer.list_item = true;
+      }
+      _ if group.destination != Destination::Text => {}
+      Token::Word(word, param) => match word {
+        "par" | "sect" | "page" => writer.end_paragraph(&group),
+        "pard" => {
+          group.style = 0;
+          group.outline_level = None;
+          group.in_table = false;
+        }
+        "s" => group.style = param.unwrap_or_default(),
+        "outlinelevel" => group.outline_level = param.map(|p| p.max(0) as usize),
+        "intbl" => group.in_table = true,
+        "cell" => writer.end_cell(),
+        "row" => writer.end_row(),
+        "ansicpg" => writer.encoding = param.and_then(code_page).or(writer.encoding),
+        "uc" => group.uc = param.unwrap_or(1).max(0) as usize,
+        "u" => {
+          let unit = param.unwrap_or_default() as i16 as u16;
+          match (surrogate.take(), unit) {
+            (_, 0xd800..=0xdbff) => surrogate = Some(unit),
+            (Some(high), 0xdc00..=0xdfff) => {
+              writer
+                .paragraph
+                .extend(char::decode_utf16([high, unit]).flatten());
+            }
+            (_, unit) => writer
+              .paragraph
+              .extend(char::decode_utf16([unit]).flatten()),
+          }
+          skip_chars = group.uc;
+        }
+        "line" => writer.paragraph.push('\n'),
+        "tab" => writer.paragraph.push('\t'),
+        "emdash" => writer.paragraph.push('—'),
+        "endash" => writer.paragraph.push('–'),
+        "bullet" => writer.paragraph.push('•'),
+        "lquote" => writer.paragraph.push('‘'),
+        "rquote" => writer.paragraph.push('’'),
+        "ldblquote" => writer.paragraph.push('“'),
+        "rdblquote" => writer.paragraph.push('”'),
+        _ => {}
+      },
+      Token::Symbol(symbol) => match symbol {
+        b'\\' | b'{' | b'}' => writer.paragraph.push(symbol as char),
+        b'~' => writer.paragraph.push(' '),
+        b'_' => writer.paragraph.push('-'),
+        b'\r' | b'\n' => writer.end_paragraph(&group),
+        _ => {}
+      },
+      Token::Hex(byte) => {
+        if skip_chars > 0 {
+          skip_chars -= 1;
+        } else {
+          writer.bytes.push(byte);
+        }
+      }
+      Token::Text(text) => {
+        let skipped = skip_chars.min(text.len());
+        skip_chars -= skipped;
+        writer.bytes.extend_from_slice(&text[skipped..]);
+      }
+    }
+  }
+  writer.flush_bytes();
+  writer.end_paragraph(&group);
+  writer.flush_table();
+
+  Ok(writer.blocks.into_markdown())
+}
+
+/// Loads RTF documents as markdown.
+#[derive(Debug, Clone)]
+pub struct RtfLoader {
+  content: String,
+}
+
+impl RtfLoader {
+  pub fn new<R: Read>(mut reader: R) -> LoaderResult<Self> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    Ok(Self {
+      content: to_markdown(&bytes)?,
+    })
+  }
+}
+
+impl Loader for RtfLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    Ok(vec![Document::new(self.content)])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_rtf() {
+    let buffer = include_bytes!("../../../fixtures/sample.rtf");
+    let documents = RtfLoader::new(&buffer[..]).unwrap().load().unwrap();
+
+    assert_eq!(documents.len(), 1);
+    assert_eq!(
+      documents[0].page_content,
+      "# Field Report\n\n\
+       The survey covered three sites along the coast in “early” spring, naïve estimates aside.\n\n\
+       ## Findings\n\n\
+       Water quality improved at every site — most of all near the ‘estuary’.\n\n\
+       - Salinity was stable\n\
+       - Turbidity dropped by half\n\n\
+       ## Measurements\n\n\
+       | Site | Temperature |\n| --- | --- |\n| North | 12.5 |\n| South | 14.0 |\n\n\
+       Next visit is planned for autumn."
+    );
+  }
+
+  #[test]
+  fn test_code_pages() {
+    let cyrillic = br"{\rtf1\ansi\ansicpg1251 \'cf\'f0\'e8\'e2\'e5\'f2\par}";
+    assert_eq!(to_markdown(cyrillic).unwrap(), "Привет");
+    // characters of double byte code pages are escaped byte by byte
+    let japanese = br"{\rtf1\ansi\ansicpg932 \'82\'b1\'82\'f1\'82\'c9\'82\'bf\'82\'cd\par}";
+    assert_eq!(to_markdown(japanese).unwrap(), "こんにちは");
+    let latin = br"{\rtf1\ansi na\'efve \'93quotes\'94\par}";
+    assert_eq!(to_markdown(latin).unwrap(), "naïve “quotes”");
+  }
+
+  #[test]
+  fn test_binary_data() {
+    // the binary data of a skipped destination closes no groups
+    let rtf = br"{\rtf1\ansi {\*\private \bin4 }}ab}text\par}";
+    assert_eq!(to_markdown(rtf).unwrap(), "text");
+  }
+
+  #[test]
+  fn test_invalid_rtf() {
+    assert!(RtfLoader::new(&b"plain text"[..]).is_err());
+  }
+}
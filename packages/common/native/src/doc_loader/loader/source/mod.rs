@@ -3,7 +3,9 @@
  */
 mod parser;
 
-pub use parser::{get_language_by_filename, LanguageParser, LanguageParserOptions};
+pub use parser::{
+  get_language_by_filename, get_language_by_name, LanguageParser, LanguageParserOptions,
+};
 
 use super::*;
 
@@ -35,6 +37,7 @@ impl Loader for SourceCodeLoader {
 
     let docs = LanguageParser::from_language(options.language)
       .with_parser_threshold(options.parser_threshold)
+      .with_class_threshold(options.class_threshold)
       .parse_code(&self.content)?;
 
     Ok(docs)
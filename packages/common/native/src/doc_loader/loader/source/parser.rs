@@ -1,7 +1,7 @@
-use std::{collections::HashMap, fmt::Debug, string::ToString};
+use std::{collections::HashMap, fmt::Debug, ops::Range, string::ToString};
 
 use strum_macros::Display;
-use tree_sitter::{Parser, Tree};
+use tree_sitter::{Node, Parser, Tree};
 
 /**
  * modified from https://github.com/Abraxas-365/langchain-rust/tree/v4.6.0/src/document_loaders
@@ -17,6 +17,83 @@ pub enum Language {
   Typescript,
   Go,
   Python,
+  Java,
+  Kotlin,
+  Scala,
+  CSharp,
+}
+
+impl Language {
+  /// Node kinds of free standing functions.
+  fn function_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Language::Rust => &["function_item"],
+      Language::C | Language::Cpp => &["function_definition"],
+      Language::Javascript | Language::Typescript => {
+        &["function_declaration", "generator_function_declaration"]
+      }
+      Language::Go => &["function_declaration", "method_declaration"],
+      Language::Python | Language::Scala => &["function_definition"],
+      Language::Kotlin => &["function_declaration"],
+      Language::Java | Language::CSharp => &[],
+    }
+  }
+
+  /// Node kinds of classes and class like declarations, which are split into
+  /// their methods when they exceed the class threshold.
+  fn class_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Language::Rust => &["impl_item", "trait_item"],
+      Language::C => &[],
+      Language::Cpp => &["class_specifier", "struct_specifier"],
+      Language::Javascript => &["class_declaration"],
+      Language::Typescript => &[
+        "class_declaration",
+        "abstract_class_declaration",
+        "interface_declaration",
+      ],
+      Language::Go => &["type_declaration"],
+      Language::Python => &["class_definition"],
+      Language::Java => &[
+        "class_declaration",
+        "interface_declaration",
+        "enum_declaration",
+        "record_declaration",
+      ],
+      Language::Kotlin => &["class_declaration", "object_declaration"],
+      Language::Scala => &["class_definition", "object_definition", "trait_definition"],
+      Language::CSharp => &[
+        "class_declaration",
+        "struct_declaration",
+        "interface_declaration",
+        "record_declaration",
+      ],
+    }
+  }
+
+  /// Node kinds of the methods inside a class body.
+  fn method_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Language::Rust => &["function_item"],
+      Language::C | Language::Go => &[],
+      Language::Cpp | Language::Python => &["function_definition"],
+      Language::Javascript | Language::Typescript => &["method_definition"],
+      Language::Java | Language::CSharp => &["method_declaration", "constructor_declaration"],
+      Language::Kotlin => &["function_declaration"],
+      Language::Scala => &["function_definition"],
+    }
+  }
+
+  /// Node kinds of modules and namespaces, which are split into their
+  /// declarations like classes into their methods.
+  fn container_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Language::Rust => &["mod_item"],
+      Language::Cpp => &["namespace_definition"],
+      Language::CSharp => &["namespace_declaration", "file_scoped_namespace_declaration"],
+      _ => &[],
+    }
+  }
 }
 
 pub enum LanguageContentTypes {
@@ -37,9 +114,21 @@ impl std::fmt::Display for LanguageContentTypes {
   }
 }
 
+#[derive(Display, Debug, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum SymbolKind {
+  Function,
+  Class,
+  Method,
+  /// Modules and namespaces
+  Module,
+}
+
 #[derive(Debug, Clone)]
 pub struct LanguageParserOptions {
   pub parser_threshold: u64,
+  /// Classes spanning more lines than this are split into per method chunks
+  pub class_threshold: u64,
   pub language: Language,
 }
 
@@ -47,6 +136,7 @@ impl Default for LanguageParserOptions {
   fn default() -> Self {
     Self {
       parser_threshold: 1000,
+      class_threshold: 200,
       language: Language::Rust,
     }
   }
@@ -92,6 +182,31 @@ pub fn get_language_by_filename(name: &str) -> LoaderResult<Language> {
     "tsx" => Language::Typescript,
     "go" => Language::Go,
     "py" => Language::Python,
+    "java" => Language::Java,
+    "kt" => Language::Kotlin,
+    "kts" => Language::Kotlin,
+    "scala" => Language::Scala,
+    "sc" => Language::Scala,
+    "cs" => Language::CSharp,
+    _ => return Err(LoaderError::UnsupportedLanguage),
+  };
+  Ok(language)
+}
+
+/// Returns the language of a notebook kernel or code fence by its name.
+pub fn get_language_by_name(name: &str) -> LoaderResult<Language> {
+  let language = match name.to_lowercase().as_str() {
+    "rust" => Language::Rust,
+    "c" => Language::C,
+    "c++" | "cpp" => Language::Cpp,
+    "javascript" | "js" => Language::Javascript,
+    "typescript" | "ts" => Language::Typescript,
+    "go" => Language::Go,
+    "python" | "python3" | "py" => Language::Python,
+    "java" => Language::Java,
+    "kotlin" => Language::Kotlin,
+    "scala" => Language::Scala,
+    "c#" | "csharp" => Language::CSharp,
     _ => return Err(LoaderError::UnsupportedLanguage),
   };
   Ok(language)
@@ -107,6 +222,10 @@ fn get_language_parser(language: &Language) -> Parser {
     Language::Typescript => tree_sitter_typescript::LANGUAGE_TSX,
     Language::Go => tree_sitter_go::LANGUAGE,
     Language::Python => tree_sitter_python::LANGUAGE,
+    Language::Java => tree_sitter_java::LANGUAGE,
+    Language::Kotlin => tree_sitter_kotlin_ng::LANGUAGE,
+    Language::Scala => tree_sitter_scala::LANGUAGE,
+    Language::CSharp => tree_sitter_c_sharp::LANGUAGE,
   };
   parser
     .set_language(&lang.into())
@@ -129,6 +248,11 @@ impl LanguageParser {
     self.parser_options.parser_threshold = threshold;
     self
   }
+
+  pub fn with_class_threshold(mut self, threshold: u64) -> Self {
+    self.parser_options.class_threshold = threshold;
+    self
+  }
 }
 
 impl LanguageParser {
@@ -152,47 +276,251 @@ impl LanguageParser {
     self.extract_functions_classes(tree, code)
   }
 
-  pub fn extract_functions_classes(
-    &self,
-    tree: Tree,
-    code: &String,
-  ) -> LoaderResult<Vec<Document>> {
+  pub fn extract_functions_classes(&self, tree: Tree, code: &str) -> LoaderResult<Vec<Document>> {
     let mut chunks = Vec::new();
 
+    let mut scope = None;
     let count = tree.root_node().child_count();
     for i in 0..count {
       let Some(node) = tree.root_node().child(i) else {
         continue;
       };
-      let source_code = node.utf8_text(code.as_bytes())?.to_string();
-      let lang_meta = (
+      self.extract_node(node, code, scope.as_deref(), &mut chunks)?;
+      // the declarations following a file scoped namespace belong to it
+      if node.kind() == "file_scoped_namespace_declaration" {
+        scope = self.classify(node, code).map(|(_, name)| name);
+      }
+    }
+    Ok(chunks)
+  }
+
+  fn extract_node(
+    &self,
+    node: Node,
+    code: &str,
+    scope: Option<&str>,
+    chunks: &mut Vec<Document>,
+  ) -> LoaderResult<()> {
+    let Some((kind, name)) = self.classify(node, code) else {
+      chunks.push(self.chunk(code, node.byte_range(), rows(node), None));
+      return Ok(());
+    };
+
+    // symbols inside modules and namespaces are qualified by their name
+    let name = [scope.unwrap_or_default(), &name]
+      .into_iter()
+      .filter(|name| !name.is_empty())
+      .collect::<Vec<_>>()
+      .join(".");
+    let lines = (node.end_position().row - node.start_position().row + 1) as u64;
+    let splits = matches!(kind, SymbolKind::Class | SymbolKind::Module);
+    let members = if splits && lines > self.parser_options.class_threshold {
+      self.members(node, kind, code)
+    } else {
+      vec![]
+    };
+    if members.is_empty() {
+      chunks.push(self.chunk(code, node.byte_range(), rows(node), Some((kind, name))));
+      return Ok(());
+    }
+
+    // the declaration, the fields of classes and the other code between the
+    // members are kept as chunks of the class or namespace around them
+    let (mut cursor, mut row) = (node.start_byte(), node.start_position().row);
+    for (member, method_name) in members {
+      if !is_blank(&code[cursor..member.start_byte()]) {
+        chunks.push(self.chunk(
+          code,
+          cursor..member.start_byte(),
+          row..member.start_position().row,
+          Some((kind, name.clone())),
+        ));
+      }
+      match method_name {
+        Some(method_name) => chunks.push(self.chunk(
+          code,
+          member.byte_range(),
+          rows(member),
+          Some((SymbolKind::Method, format!("{}.{}", name, method_name))),
+        )),
+        None => self.extract_node(member, code, Some(&name), chunks)?,
+      }
+      (cursor, row) = (member.end_byte(), member.end_position().row);
+    }
+    if !is_blank(&code[cursor..node.end_byte()]) {
+      chunks.push(self.chunk(
+        code,
+        cursor..node.end_byte(),
+        row..node.end_position().row,
+        Some((kind, name)),
+      ));
+    }
+    Ok(())
+  }
+
+  /// Returns the symbol kind and name of function, class and module nodes,
+  /// anonymous namespaces have an empty name.
+  fn classify(&self, node: Node, code: &str) -> Option<(SymbolKind, String)> {
+    let language = &self.parser_options.language;
+    if language.container_kinds().contains(&node.kind()) {
+      // namespaces keep their qualified names such as `Company.Product`
+      let name = node
+        .child_by_field_name("name")
+        .and_then(|name| name.utf8_text(code.as_bytes()).ok())
+        .unwrap_or_default();
+      return Some((SymbolKind::Module, name.to_string()));
+    }
+    let definition = unwrap_definition(node);
+    let kind = if language.function_kinds().contains(&definition.kind()) {
+      SymbolKind::Function
+    } else if language.class_kinds().contains(&definition.kind()) {
+      SymbolKind::Class
+    } else if let Some(declarator) = arrow_function_declarator(definition) {
+      return Some((SymbolKind::Function, symbol_name(declarator, code)?));
+    } else {
+      return None;
+    };
+    Some((kind, symbol_name(definition, code)?))
+  }
+
+  /// Returns the members a class or module is split into, the methods of
+  /// classes together with their names and every declaration of modules.
+  fn members<'a>(
+    &self,
+    node: Node<'a>,
+    kind: SymbolKind,
+    code: &str,
+  ) -> Vec<(Node<'a>, Option<String>)> {
+    let definition = unwrap_definition(node);
+    let mut cursor = definition.walk();
+    let Some(body) = definition.child_by_field_name("body").or_else(|| {
+      // modules without a body, such as `mod name;`, have no members
+      (kind == SymbolKind::Class)
+        .then(|| {
+          definition
+            .named_children(&mut cursor)
+            .find(|child| child.kind().ends_with("body") || child.kind().ends_with("_list"))
+        })
+        .flatten()
+    }) else {
+      return vec![];
+    };
+
+    let mut cursor = body.walk();
+    let children = body.named_children(&mut cursor);
+    if kind == SymbolKind::Module {
+      return children.map(|child| (child, None)).collect();
+    }
+    let method_kinds = self.parser_options.language.method_kinds();
+    children
+      .filter(|child| method_kinds.contains(&unwrap_definition(*child).kind()))
+      .filter_map(|child| Some((child, Some(symbol_name(unwrap_definition(child), code)?))))
+      .collect()
+  }
+
+  /// Returns a chunk of `code`, `rows` are the (0-based) rows of the start
+  /// and the end of `range`.
+  fn chunk(
+    &self,
+    code: &str,
+    range: Range<usize>,
+    rows: Range<usize>,
+    symbol: Option<(SymbolKind, String)>,
+  ) -> Document {
+    let content_type = if symbol.is_some() {
+      LanguageContentTypes::FunctionsImpls
+    } else {
+      LanguageContentTypes::SimplifiedCode
+    };
+    let mut metadata = HashMap::from([
+      (
         "language".to_string(),
         serde_json::Value::from(self.parser_options.language.to_string()),
+      ),
+      (
+        "content_type".to_string(),
+        serde_json::Value::from(content_type.to_string()),
+      ),
+      ("start".to_string(), serde_json::Value::from(range.start)),
+      ("end".to_string(), serde_json::Value::from(range.end)),
+      (
+        "start_line".to_string(),
+        serde_json::Value::from(rows.start + 1),
+      ),
+      (
+        "end_line".to_string(),
+        serde_json::Value::from(rows.end + 1),
+      ),
+    ]);
+    if let Some((kind, name)) = symbol.filter(|(_, name)| !name.is_empty()) {
+      metadata.insert("symbol".to_string(), serde_json::Value::from(name));
+      metadata.insert(
+        "symbol_kind".to_string(),
+        serde_json::Value::from(kind.to_string()),
       );
-      if node.kind() == "function_item" || node.kind() == "impl_item" {
-        let doc = Document::new(source_code).with_metadata(HashMap::from([
-          lang_meta.clone(),
-          (
-            "content_type".to_string(),
-            serde_json::Value::from(LanguageContentTypes::FunctionsImpls.to_string()),
-          ),
-        ]));
-        chunks.push(doc);
-      } else {
-        let doc = Document::new(source_code).with_metadata(HashMap::from([
-          lang_meta.clone(),
-          (
-            "content_type".to_string(),
-            serde_json::Value::from(LanguageContentTypes::SimplifiedCode.to_string()),
-          ),
-        ]));
-        chunks.push(doc);
-      }
     }
-    Ok(chunks)
+    Document::new(&code[range]).with_metadata(metadata)
   }
 }
 
+fn rows(node: Node) -> Range<usize> {
+  node.start_position().row..node.end_position().row
+}
+
+fn is_blank(code: &str) -> bool {
+  code
+    .trim_matches(|c: char| c.is_whitespace() || c == '}' || c == ';')
+    .is_empty()
+}
+
+/// Skips the decorators of python definitions and the `export` keyword of
+/// javascript declarations.
+fn unwrap_definition(node: Node) -> Node {
+  match node.kind() {
+    "decorated_definition" => node.child_by_field_name("definition"),
+    "export_statement" => node.child_by_field_name("declaration"),
+    _ => None,
+  }
+  .map_or(node, unwrap_definition)
+}
+
+/// Matches `const name = () => {}` style function declarations.
+fn arrow_function_declarator(node: Node) -> Option<Node> {
+  if !matches!(node.kind(), "lexical_declaration" | "variable_declaration")
+    || node.named_child_count() != 1
+  {
+    return None;
+  }
+  let declarator = node.named_child(0)?;
+  let value = declarator.child_by_field_name("value")?;
+  matches!(
+    value.kind(),
+    "arrow_function" | "function_expression" | "function"
+  )
+  .then_some(declarator)
+}
+
+fn symbol_name(node: Node, code: &str) -> Option<String> {
+  if node.kind().ends_with("identifier") {
+    return node
+      .utf8_text(code.as_bytes())
+      .ok()
+      .map(ToString::to_string);
+  }
+  for field in ["name", "declarator", "type"] {
+    if let Some(child) = node.child_by_field_name(field) {
+      return symbol_name(child, code);
+    }
+  }
+  let mut cursor = node.walk();
+  let children = node.named_children(&mut cursor).collect::<Vec<_>>();
+  children
+    .iter()
+    .find(|child| child.kind().ends_with("identifier"))
+    .or(children.first())
+    .and_then(|child| symbol_name(*child, code))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -242,5 +570,148 @@ mod tests {
       documents[1].metadata.get("content_type").unwrap(),
       LanguageContentTypes::SimplifiedCode.to_string().as_str()
     );
+    for doc in documents {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert_eq!(&code[start..end], doc.page_content);
+    }
+  }
+  #[test]
+  fn test_symbol_chunks() {
+    let code = r#"
+import os
+
+@cache
+def load(path):
+    return os.path.join(path)
+
+class Loader:
+    root = "/"
+
+    def __init__(self, root):
+        self.root = root
+
+    @property
+    def name(self):
+        return self.root
+"#;
+
+    let mut parser = LanguageParser::from_language(Language::Python)
+      .with_parser_threshold(0)
+      .with_class_threshold(5);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+
+    let symbols = documents
+      .iter()
+      .map(|doc| {
+        (
+          doc.metadata.get("symbol").and_then(|v| v.as_str()),
+          doc.metadata.get("symbol_kind").and_then(|v| v.as_str()),
+          doc.metadata["start_line"].as_u64().unwrap(),
+          doc.metadata["end_line"].as_u64().unwrap(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      symbols,
+      vec![
+        (None, None, 2, 2),
+        (Some("load"), Some("function"), 4, 6),
+        (Some("Loader"), Some("class"), 8, 11),
+        (Some("Loader.__init__"), Some("method"), 11, 12),
+        (Some("Loader.name"), Some("method"), 14, 16),
+      ]
+    );
+    assert!(documents[2].page_content.contains("root = \"/\""));
+    for doc in documents {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert_eq!(&code[start..end], doc.page_content);
+    }
+
+    let code = "export function a() {}\nconst b = () => 1;\nexport class C {}\n";
+    let mut parser = LanguageParser::from_language(Language::Typescript).with_parser_threshold(0);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+    assert_eq!(
+      documents
+        .iter()
+        .map(|doc| doc.metadata["symbol"].as_str().unwrap())
+        .collect::<Vec<_>>(),
+      vec!["a", "b", "C"]
+    );
+  }
+
+  fn symbols(documents: &[Document]) -> Vec<(Option<&str>, Option<&str>)> {
+    documents
+      .iter()
+      .map(|doc| {
+        (
+          doc.metadata.get("symbol").and_then(|v| v.as_str()),
+          doc.metadata.get("symbol_kind").and_then(|v| v.as_str()),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_module_chunks() {
+    let code = include_str!("../../../../fixtures/sample.cs");
+    let mut parser = LanguageParser::from_language(Language::CSharp).with_parser_threshold(0);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+    // small namespaces are kept whole
+    assert_eq!(documents.len(), 2);
+    assert_eq!(
+      documents[1].page_content,
+      code[code.find("namespace").unwrap()..].trim_end()
+    );
+    assert_eq!(documents[1].metadata["symbol_kind"], "module");
+
+    let mut parser = parser.with_class_threshold(2);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+    assert_eq!(
+      symbols(&documents),
+      vec![
+        (None, None),
+        (Some("Sample"), Some("module")),
+        (Some("Sample.Program"), Some("class")),
+        (Some("Sample.Program.Main"), Some("method")),
+      ]
+    );
+    // the header of the namespace is kept
+    assert_eq!(documents[1].page_content, "namespace Sample\n{\n    ");
+    assert_eq!(documents[1].metadata["start_line"], 3);
+    assert_eq!(documents[1].metadata["end_line"], 5);
+
+    let code = "namespace Company.Product;\n\nclass Program {}\n";
+    let mut parser = LanguageParser::from_language(Language::CSharp).with_parser_threshold(0);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+    assert_eq!(
+      symbols(&documents),
+      vec![
+        (Some("Company.Product"), Some("module")),
+        (Some("Company.Product.Program"), Some("class")),
+      ]
+    );
+
+    let code = "mod loader;\n\npub mod splitter {\n    fn split() {}\n}\n";
+    let mut parser = LanguageParser::from_language(Language::Rust)
+      .with_parser_threshold(0)
+      .with_class_threshold(1);
+    let documents = parser.parse_code(&code.to_string()).unwrap();
+    assert_eq!(
+      symbols(&documents),
+      vec![
+        (Some("loader"), Some("module")),
+        (Some("splitter"), Some("module")),
+        (Some("splitter.split"), Some("function")),
+      ]
+    );
+    assert_eq!(documents[0].page_content, "mod loader;");
+    assert_eq!(documents[1].page_content, "pub mod splitter {\n    ");
+    for doc in documents {
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert_eq!(&code[start..end], doc.page_content);
+    }
   }
 }
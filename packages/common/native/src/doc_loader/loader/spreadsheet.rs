@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use calamine::{open_workbook_auto_from_rs, Cell, Data, DataRef, Reader, Sheets, XlsxError};
+use quick_xml::{
+  events::{BytesStart, Event},
+  Reader as XmlReader,
+};
+use serde_json::Value;
+
+use super::{
+  package::{attribute, Package},
+  *,
+};
+
+/// Cells of a sheet read at most, counting the empty cells between the used
+/// ones, which calamine allocates as well
+const MAX_SHEET_CELLS: u64 = 5_000_000;
+
+#[derive(Debug, Clone)]
+struct Sheet {
+  name: Option<String>,
+  rows: Vec<Vec<String>>,
+}
+
+/// Loads XLSX, XLS and ODS workbooks as well as CSV and TSV files, every
+/// sheet is rendered as a markdown table with its first row as header.
+#[derive(Debug, Clone)]
+pub struct SpreadsheetLoader {
+  sheets: Vec<Sheet>,
+}
+
+impl SpreadsheetLoader {
+  /// Reads a workbook. Sheets whose used cells span more than
+  /// `MAX_SHEET_CELLS` cells fail with `LoaderError::SheetTooLarge`, XLS
+  /// sheets are small enough by the limits of the format.
+  pub fn new<R: Read + Seek + Clone>(reader: R) -> LoaderResult<Self> {
+    // XLSX and ODS workbooks are zip packages, whose files may expand far
+    // beyond the size of the workbook
+    if let Ok(mut package) = Package::new(reader.clone()) {
+      package.check_size()?;
+      if let Some(content) = Package::new(reader.clone())?.read("content.xml")? {
+        check_cells(ods_cells(&content)?)?;
+      }
+    }
+
+    let mut workbook = open_workbook_auto_from_rs(reader)?;
+    let sheets = workbook
+      .sheet_names()
+      .into_iter()
+      .map(|name| {
+        let rows = match &mut workbook {
+          Sheets::Xlsx(xlsx) => match xlsx.worksheet_cells_reader(&name) {
+            Ok(mut cells) => sheet_rows(|| cells.next_cell())?,
+            // chartsheets have no cells
+            Err(XlsxError::NotAWorksheet(_)) => vec![],
+            Err(e) => return Err(calamine::Error::from(e).into()),
+          },
+          Sheets::Xlsb(xlsb) => {
+            let mut cells = xlsb
+              .worksheet_cells_reader(&name)
+              .map_err(calamine::Error::from)?;
+            sheet_rows(|| cells.next_cell())?
+          }
+          workbook => workbook
+            .worksheet_range(&name)?
+            .rows()
+            .map(|row| row.iter().map(ToString::to_string).collect())
+            .collect(),
+        };
+        Ok(Sheet {
+          name: Some(name),
+          rows,
+        })
+      })
+      .collect::<LoaderResult<_>>()?;
+    Ok(Self { sheets })
+  }
+
+  /// Reads delimiter separated values, `b','` for CSV and `b'\t'` for TSV.
+  pub fn from_csv<R: Read>(reader: R, delimiter: u8) -> LoaderResult<Self> {
+    let rows = csv::ReaderBuilder::new()
+      .delimiter(delimiter)
+      .has_headers(false)
+      .flexible(true)
+      .from_reader(reader)
+      .records()
+      .map(|record| Ok(record?.iter().map(ToString::to_string).collect()))
+      .collect::<LoaderResult<_>>()?;
+    Ok(Self {
+      sheets: vec![Sheet { name: None, rows }],
+    })
+  }
+}
+
+fn check_cells(cells: u64) -> LoaderResult<()> {
+  if cells > MAX_SHEET_CELLS {
+    return Err(LoaderError::SheetTooLarge(MAX_SHEET_CELLS));
+  }
+  Ok(())
+}
+
+/// Lays the non-empty cells returned by `next_cell` out in rows, from the
+/// first row and column with a cell to the last ones.
+fn sheet_rows<'a, E>(
+  mut next_cell: impl FnMut() -> Result<Option<Cell<DataRef<'a>>>, E>,
+) -> LoaderResult<Vec<Vec<String>>>
+where
+  calamine::Error: From<E>,
+{
+  let mut cells = vec![];
+  while let Some(cell) = next_cell().map_err(calamine::Error::from)? {
+    if !matches!(cell.get_value(), DataRef::Empty) {
+      cells.push(cell);
+    }
+  }
+  let positions = || cells.iter().map(Cell::get_position);
+  let (Some(first_row), Some(last_row)) = (
+    positions().map(|(row, _)| row).min(),
+    positions().map(|(row, _)| row).max(),
+  ) else {
+    return Ok(vec![]);
+  };
+  let first_col = positions().map(|(_, col)| col).min().unwrap_or_default();
+  let last_col = positions().map(|(_, col)| col).max().unwrap_or_default();
+  let (height, width) = (last_row - first_row + 1, last_col - first_col + 1);
+  // a few cells far apart span a range too large to lay out
+  check_cells(height as u64 * width as u64)?;
+
+  let mut rows = vec![vec![String::new(); width as usize]; height as usize];
+  for cell in cells {
+    let (row, col) = cell.get_position();
+    rows[(row - first_row) as usize][(col - first_col) as usize] =
+      Data::from(cell.get_value().clone()).to_string();
+  }
+  Ok(rows)
+}
+
+/// Returns the number of cells of the largest table in the `content.xml` of
+/// an ODS workbook, counting repeated rows and columns up to the last cell
+/// with content like calamine does when it lays the table out.
+fn ods_cells(content: &str) -> LoaderResult<u64> {
+  let repeated = |e: &BytesStart, name: &str| -> LoaderResult<u64> {
+    Ok(
+      attribute(e, name)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1),
+    )
+  };
+  let mut reader = XmlReader::from_str(content);
+  let (mut max, mut height, mut width) = (0u64, 0u64, 0u64);
+  let (mut row, mut row_repeats, mut col) = (0u64, 1u64, 0u64);
+  loop {
+    let (e, empty) = match reader.read_event()? {
+      Event::Start(e) => (e, false),
+      Event::Empty(e) => (e, true),
+      Event::End(e) => {
+        match e.local_name().as_ref() {
+          b"table-row" => row = row.saturating_add(row_repeats),
+          b"table" => max = max.max(height.saturating_mul(width)),
+          _ => {}
+        }
+        continue;
+      }
+      Event::Eof => break,
+      _ => continue,
+    };
+    match e.local_name().as_ref() {
+      b"table" => (height, width, row) = (0, 0, 0),
+      b"table-row" => {
+        (row_repeats, col) = (repeated(&e, "table:number-rows-repeated")?, 0);
+        if empty {
+          row = row.saturating_add(row_repeats);
+        }
+      }
+      b"table-cell" | b"covered-table-cell" => {
+        let cols = repeated(&e, "table:number-columns-repeated")?;
+        col = col.saturating_add(cols);
+        if !empty || attribute(&e, "office:value-type")?.is_some() {
+          height = height.max(row.saturating_add(row_repeats));
+          width = width.max(col);
+        }
+      }
+      _ => {}
+    }
+  }
+  Ok(max)
+}
+
+fn escape_cell(cell: &str) -> String {
+  cell
+    .trim()
+    .replace('|', "\\|")
+    .replace("\r\n", " ")
+    .replace('\n', " ")
+}
+
+pub(super) fn to_markdown_table(rows: &[Vec<String>]) -> Option<String> {
+  let rows = rows
+    .iter()
+    .filter(|row| row.iter().any(|cell| !cell.trim().is_empty()))
+    .collect::<Vec<_>>();
+  let width = rows
+    .iter()
+    .filter_map(|row| row.iter().rposition(|cell| !cell.trim().is_empty()))
+    .max()?
+    + 1;
+
+  let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+  let mut lines = Vec::with_capacity(rows.len() + 1);
+  for (i, row) in rows.iter().enumerate() {
+    lines.push(line(
+      (0..width)
+        .map(|col| {
+          row
+            .get(col)
+            .map(|cell| escape_cell(cell))
+            .unwrap_or_default()
+        })
+        .collect(),
+    ));
+    if i == 0 {
+      lines.push(line(vec!["---".to_string(); width]));
+    }
+  }
+  Some(lines.join("\n"))
+}
+
+impl Loader for SpreadsheetLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let mut offset = 0;
+    Ok(
+      self
+        .sheets
+        .into_iter()
+        .filter_map(|sheet| {
+          let table = to_markdown_table(&sheet.rows)?;
+          // sheets are laid out one after another in the extracted text
+          let mut metadata = HashMap::from([("start".to_string(), Value::from(offset))]);
+          offset += table.len();
+          if let Some(name) = sheet.name {
+            metadata.insert("sheet".to_string(), Value::from(name));
+          }
+          Some(Document::new(table).with_metadata(metadata))
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Cursor, Write};
+
+  use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+  use super::*;
+
+  #[test]
+  fn test_parse_xlsx() {
+    let buffer = include_bytes!("../../../fixtures/sample.xlsx");
+    let documents = SpreadsheetLoader::new(Cursor::new(buffer.to_vec()))
+      .unwrap()
+      .load()
+      .unwrap();
+
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].metadata["sheet"], "Products");
+    assert_eq!(
+      documents[0].page_content,
+      "| Name | Price | Stock |\n| --- | --- | --- |\n| Pen | 1.5 | 30 |\n| Notebook \\| A5 | 4 | 12 |"
+    );
+    assert_eq!(documents[1].metadata["sheet"], "Notes");
+  }
+
+  #[test]
+  fn test_sheet_limits() {
+    // a cell at the last row and column of the sheet
+    let mut archive = ZipArchive::new(Cursor::new(
+      include_bytes!("../../../fixtures/sample.xlsx").to_vec(),
+    ))
+    .unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    for i in 0..archive.len() {
+      let mut file = archive.by_index(i).unwrap();
+      let mut content = String::new();
+      file.read_to_string(&mut content).unwrap();
+      if file.name() == "xl/worksheets/sheet1.xml" {
+        content = content.replace(
+          "</sheetData>",
+          r#"<row r="1048576"><c r="XFD1048576"><v>1</v></c></row></sheetData>"#,
+        );
+      }
+      writer
+        .start_file(file.name(), SimpleFileOptions::default())
+        .unwrap();
+      writer.write_all(content.as_bytes()).unwrap();
+    }
+    let buffer = writer.finish().unwrap().into_inner();
+    assert!(matches!(
+      SpreadsheetLoader::new(Cursor::new(buffer)),
+      Err(LoaderError::SheetTooLarge(MAX_SHEET_CELLS))
+    ));
+
+    let content = |cell: &str| {
+      format!(
+        r#"<office:document-content><office:body><office:spreadsheet><table:table>
+          <table:table-row><table:table-cell office:value-type="string"><text:p>a</text:p></table:table-cell></table:table-row>
+          <table:table-row table:number-rows-repeated="1048575">{}</table:table-row>
+        </table:table></office:spreadsheet></office:body></office:document-content>"#,
+        cell
+      )
+    };
+    // empty rows and columns filling the rest of the sheet are not laid out
+    let filler = r#"<table:table-cell table:number-columns-repeated="1024"/>"#;
+    assert_eq!(ods_cells(&content(filler)).unwrap(), 1);
+    let repeated = r#"<table:table-cell table:number-columns-repeated="1024" office:value-type="float" office:value="1"/>"#;
+    assert_eq!(ods_cells(&content(repeated)).unwrap(), 1048576 * 1024);
+  }
+
+  #[test]
+  fn test_parse_csv() {
+    let csv = "name,age\n\"Doe, John\",42\n\nJane,\n";
+    let documents = SpreadsheetLoader::from_csv(csv.as_bytes(), b',')
+      .unwrap()
+      .load()
+      .unwrap();
+
+    assert_eq!(documents.len(), 1);
+    assert!(!documents[0].metadata.contains_key("sheet"));
+    assert_eq!(
+      documents[0].page_content,
+      "| name | age |\n| --- | --- |\n| Doe, John | 42 |\n| Jane |  |"
+    );
+  }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use strum_macros::Display;
+
+use super::*;
+
+/// Formats of structured data files, which are all read into JSON values.
+#[derive(Display, Debug, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+pub enum DataFormat {
+  Json,
+  Yaml,
+  Toml,
+}
+
+fn from_yaml(value: serde_yaml_ng::Value) -> Value {
+  match value {
+    serde_yaml_ng::Value::Null => Value::Null,
+    serde_yaml_ng::Value::Bool(b) => Value::Bool(b),
+    serde_yaml_ng::Value::Number(n) => {
+      if let Some(n) = n.as_i64() {
+        Value::from(n)
+      } else if let Some(n) = n.as_u64() {
+        Value::from(n)
+      } else {
+        n.as_f64().map(Value::from).unwrap_or(Value::Null)
+      }
+    }
+    serde_yaml_ng::Value::String(s) => Value::String(s),
+    serde_yaml_ng::Value::Sequence(items) => {
+      Value::Array(items.into_iter().map(from_yaml).collect())
+    }
+    serde_yaml_ng::Value::Mapping(mapping) => Value::Object(
+      mapping
+        .into_iter()
+        .map(|(key, value)| {
+          // keys are not restricted to strings in YAML
+          let key = match key {
+            serde_yaml_ng::Value::String(key) => key,
+            key => serde_yaml_ng::to_string(&key)
+              .map(|key| key.trim_end().to_string())
+              .unwrap_or_default(),
+          };
+          (key, from_yaml(value))
+        })
+        .collect(),
+    ),
+    serde_yaml_ng::Value::Tagged(tagged) => from_yaml(tagged.value),
+  }
+}
+
+fn from_toml(value: toml::Value) -> Value {
+  match value {
+    toml::Value::String(s) => Value::String(s),
+    toml::Value::Integer(n) => Value::from(n),
+    toml::Value::Float(n) => Value::from(n),
+    toml::Value::Boolean(b) => Value::Bool(b),
+    toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+    toml::Value::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+    toml::Value::Table(table) => Value::Object(
+      table
+        .into_iter()
+        .map(|(key, value)| (key, from_toml(value)))
+        .collect::<Map<_, _>>(),
+    ),
+  }
+}
+
+impl DataFormat {
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    match extension.to_lowercase().as_str() {
+      "json" => Some(Self::Json),
+      "yaml" | "yml" => Some(Self::Yaml),
+      "toml" => Some(Self::Toml),
+      _ => None,
+    }
+  }
+
+  /// Parses `text`, YAML streams of several documents are read as an array
+  /// of these documents.
+  pub fn parse(&self, text: &str) -> LoaderResult<Value> {
+    match self {
+      Self::Json => Ok(serde_json::from_str(text)?),
+      Self::Yaml => {
+        let mut documents = serde_yaml_ng::Deserializer::from_str(text)
+          .map(|document| Ok(from_yaml(serde_yaml_ng::Value::deserialize(document)?)))
+          .collect::<LoaderResult<Vec<_>>>()?;
+        Ok(match documents.len() {
+          0 => Value::Null,
+          1 => documents.remove(0),
+          _ => Value::Array(documents),
+        })
+      }
+      Self::Toml => Ok(from_toml(text.parse::<toml::Value>()?)),
+    }
+  }
+
+  /// Renders `value` in this format, values TOML cannot represent, such as
+  /// `null` or a top level array, are rendered as JSON.
+  pub fn render<T: Serialize + ?Sized>(&self, value: &T) -> String {
+    let rendered = match self {
+      Self::Json => None,
+      Self::Yaml => serde_yaml_ng::to_string(value).ok(),
+      Self::Toml => toml::to_string_pretty(value).ok(),
+    };
+    rendered
+      .or_else(|| serde_json::to_string_pretty(value).ok())
+      .unwrap_or_default()
+      .trim_end()
+      .to_string()
+  }
+}
+
+/// Loads JSON, YAML and TOML files, which are meant to be split by the
+/// `StructuredSplitter` along their objects and arrays. The splitter is
+/// handed the value parsed by the loader.
+#[derive(Debug, Clone)]
+pub struct StructuredLoader {
+  content: String,
+  format: DataFormat,
+  value: Value,
+}
+
+impl StructuredLoader {
+  /// Fails if `content` is not valid in the given format.
+  pub fn new(content: &str, format: DataFormat) -> LoaderResult<Self> {
+    Ok(Self {
+      content: content.to_string(),
+      format,
+      value: format.parse(content)?,
+    })
+  }
+
+  fn document(content: String, format: DataFormat) -> Document {
+    let metadata = HashMap::from([("format".to_string(), Value::from(format.to_string()))]);
+    Document::new(content).with_metadata(metadata)
+  }
+}
+
+impl Loader for StructuredLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    Ok(vec![Self::document(self.content, self.format)])
+  }
+
+  fn load_and_split<TS: TextSplitter + 'static>(self, splitter: TS) -> LoaderResult<Vec<Document>>
+  where
+    Self: Sized,
+  {
+    let document = Self::document(self.content, self.format);
+    Ok(splitter.split_parsed(document, &self.value)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_structured() {
+    let yaml = "kind: Service\nports: [80, 443]\n---\nkind: Deployment\n1: one\n";
+    assert_eq!(
+      DataFormat::Yaml.parse(yaml).unwrap(),
+      serde_json::json!([
+        { "kind": "Service", "ports": [80, 443] },
+        { "kind": "Deployment", "1": "one" }
+      ])
+    );
+
+    let toml = "title = \"Release\"\n\n[release]\ndate = 2024-03-05\n";
+    let value = DataFormat::Toml.parse(toml).unwrap();
+    assert_eq!(value["release"]["date"], "2024-03-05");
+    assert_eq!(
+      DataFormat::Toml.render(&value),
+      "title = \"Release\"\n\n[release]\ndate = \"2024-03-05\""
+    );
+
+    assert!(StructuredLoader::new("{\"a\": 1,}", DataFormat::Json).is_err());
+    let documents = StructuredLoader::new("{\"a\": 1}", DataFormat::Json)
+      .unwrap()
+      .load()
+      .unwrap();
+    assert_eq!(documents[0].metadata["format"], "json");
+
+    let yaml = "servers:\n  - name: alpha\n  - name: beta\n";
+    let splitter = StructuredSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(12)
+        .with_chunk_overlap(0),
+    );
+    let documents = StructuredLoader::new(yaml, DataFormat::Yaml)
+      .unwrap()
+      .load_and_split(splitter)
+      .unwrap();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[1].page_content, "servers:\n- name: beta");
+    assert_eq!(documents[1].metadata["json_pointer"], "/servers/1");
+    assert_eq!(documents[1].metadata["format"], "yaml");
+  }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::*;
+
+/// A cue of a subtitle file, the times are in milliseconds from the start of
+/// the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+  pub start_ms: u64,
+  pub end_ms: u64,
+  pub text: String,
+}
+
+/// Parses timestamps such as `01:02:03,456` (SRT) or `02:03.456` (WebVTT).
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+  let (time, millis) = timestamp.trim().split_once([',', '.'])?;
+  let mut seconds = 0;
+  for part in time.split(':') {
+    seconds = seconds * 60 + part.parse::<u64>().ok()?;
+  }
+  let millis = format!("{:0<3}", millis.get(..3.min(millis.len()))?);
+  Some(seconds * 1000 + millis.parse::<u64>().ok()?)
+}
+
+/// Parses the timing line of a cue, which WebVTT may follow with settings.
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+  let (start, rest) = line.split_once("-->")?;
+  let end = rest.split_whitespace().next()?;
+  Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Removes the markup of a line of cue text. WebVTT voices become a prefix
+/// naming the speaker, other tags as well as SRT position codes are dropped.
+fn clean_line(line: &str) -> String {
+  let mut text = String::new();
+  let mut rest = line;
+  while let Some(open) = rest.find(['<', '{']) {
+    text.push_str(&rest[..open]);
+    let close = if rest[open..].starts_with('<') {
+      '>'
+    } else {
+      '}'
+    };
+    let Some(len) = rest[open..].find(close) else {
+      rest = &rest[open..];
+      break;
+    };
+    let tag = &rest[open + 1..open + len];
+    // `<v.loud Anna>` has classes before the name
+    let voice = tag.strip_prefix("v ").or_else(|| {
+      tag
+        .strip_prefix("v.")
+        .and_then(|v| v.split_once(' '))
+        .map(|(_, name)| name)
+    });
+    if let Some(name) = voice.map(str::trim) {
+      if !name.is_empty() && text.trim().is_empty() {
+        text = format!("{}: ", name);
+      }
+    } else if close == '}' && !tag.starts_with('\\') {
+      // braces that are not position codes are text
+      text.push_str(&rest[open..open + len + 1]);
+    }
+    rest = &rest[open + len + 1..];
+  }
+  text.push_str(rest);
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&nbsp;", " ")
+    .replace("&amp;", "&")
+    .trim()
+    .to_string()
+}
+
+/// Parses the cues of SRT and WebVTT files. Blocks without a timing line, like
+/// the WebVTT header or `NOTE` and `STYLE` blocks, are skipped.
+pub fn parse_cues(content: &str) -> Vec<Cue> {
+  let content = content.replace("\r\n", "\n").replace('\r', "\n");
+  let mut cues = vec![];
+  for block in content.split("\n\n") {
+    let mut lines = block.lines().skip_while(|line| line.trim().is_empty());
+    if lines.clone().next().is_some_and(|line| {
+      ["NOTE", "STYLE", "REGION"]
+        .iter()
+        .any(|k| line.starts_with(k))
+    }) {
+      continue;
+    }
+    // the index of SRT cues and the identifier of WebVTT cues are optional
+    let Some((start_ms, end_ms)) = lines.find_map(parse_timing) else {
+      continue;
+    };
+    let text = lines
+      .map(clean_line)
+      .filter(|line| !line.is_empty())
+      .collect::<Vec<_>>()
+      .join(" ");
+    if !text.is_empty() {
+      cues.push(Cue {
+        start_ms,
+        end_ms,
+        text,
+      });
+    }
+  }
+  cues
+}
+
+/// Loads SRT and WebVTT files, emitting one document per cue that carries
+/// its `start_ms` and `end_ms`. The cues are meant to be merged by the
+/// `TranscriptSplitter`, their offsets are the ones in the transcript made of
+/// the cues joined by line breaks.
+#[derive(Debug, Clone)]
+pub struct SubtitleLoader {
+  cues: Vec<Cue>,
+}
+
+impl SubtitleLoader {
+  /// Fails if `content` has no cues.
+  pub fn new(content: &str) -> LoaderResult<Self> {
+    let cues = parse_cues(content);
+    if cues.is_empty() {
+      return Err(LoaderError::Other("No subtitle cues found".into()));
+    }
+    Ok(Self { cues })
+  }
+}
+
+impl Loader for SubtitleLoader {
+  fn load(self) -> LoaderResult<Vec<Document>> {
+    let mut start = 0;
+    Ok(
+      self
+        .cues
+        .into_iter()
+        .map(|cue| {
+          let metadata = HashMap::from([
+            ("start".to_string(), Value::from(start)),
+            ("start_ms".to_string(), Value::from(cue.start_ms)),
+            ("end_ms".to_string(), Value::from(cue.end_ms)),
+          ]);
+          start += cue.text.len() + 1;
+          Document::new(cue.text).with_metadata(metadata)
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_timestamp() {
+    assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+    assert_eq!(parse_timestamp("02:03.4"), Some(123_400));
+    assert_eq!(parse_timestamp("00:00:01"), None);
+    assert_eq!(
+      parse_timing("00:01.000 --> 00:04.250 align:start position:10%"),
+      Some((1000, 4250))
+    );
+  }
+
+  #[test]
+  fn test_parse_cues() {
+    let srt = include_str!("../../../fixtures/sample.srt");
+    let cues = parse_cues(srt);
+    assert_eq!(
+      cues[0],
+      Cue {
+        start_ms: 1000,
+        end_ms: 4200,
+        text: "Good morning everyone, let's get started.".into(),
+      }
+    );
+    assert!(cues.iter().all(|cue| !cue.text.contains(['<', '{'])));
+
+    let vtt = include_str!("../../../fixtures/sample.vtt");
+    let cues = parse_cues(vtt);
+    assert_eq!(cues[0].start_ms, 1000);
+    assert!(cues[0].text.starts_with("Alice: "));
+    assert!(cues
+      .windows(2)
+      .all(|pair| pair[0].start_ms <= pair[1].start_ms));
+
+    let documents = SubtitleLoader::new(vtt).unwrap().load().unwrap();
+    let transcript = cues
+      .iter()
+      .map(|cue| cue.text.as_str())
+      .collect::<Vec<_>>()
+      .join("\n");
+    for document in documents {
+      let start = document.metadata["start"].as_u64().unwrap() as usize;
+      assert_eq!(
+        &transcript[start..start + document.page_content.len()],
+        document.page_content
+      );
+    }
+    assert!(SubtitleLoader::new("WEBVTT\n\nNOTE nothing here").is_err());
+  }
+}
@@ -1,14 +1,25 @@
+mod dedup;
 mod document;
 mod error;
 mod loader;
+mod registry;
 mod splitter;
 mod types;
 
-pub use document::{Chunk, Doc};
+use dedup::Deduplicator;
+pub use document::{Chunk, Doc, DocOptions, SplitterKind};
 pub use error::{LoaderError, LoaderResult};
 use loader::{
-  get_language_by_filename, DocxLoader, HtmlLoader, LanguageParserOptions, Loader,
-  PdfExtractLoader, SourceCodeLoader, TextLoader, Url,
+  decode_text, get_language_by_filename, html_metadata, iso_639_1, normalize_language, Archive,
+  ArchiveBudget, DataFormat, Deadline, DocxLoader, EmailLoader, EpubLoader, HtmlLoader,
+  LanguageParserOptions, Loader, NotebookLoader, OdtLoader, PdfExtractLoader, PresentationLoader,
+  RtfLoader, SourceCodeLoader, SpreadsheetLoader, StructuredLoader, SubtitleLoader, TextLoader,
+  Url,
 };
-use splitter::{MarkdownSplitter, TextSplitter, TextSplitterError, TokenSplitter};
-use types::Document;
+pub use registry::{LoaderEntry, LoaderRegistry};
+use splitter::{
+  parse_heading, update_heading_stack, MarkdownSplitter, SentenceSplitter, SplitterOptions,
+  StructuredSplitter, TableSplitter, TextSplitter, TextSplitterError, TokenSplitter,
+  TranscriptSplitter,
+};
+pub use types::{DocMetadata, Document};
@@ -0,0 +1,250 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, LazyLock, PoisonError, RwLock},
+};
+
+use super::*;
+
+type LoadFn = dyn Fn(&str, &[u8], &DocOptions) -> LoaderResult<Vec<Document>> + Send + Sync;
+type SniffFn = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+static GLOBAL: LazyLock<RwLock<Arc<LoaderRegistry>>> = LazyLock::new(Default::default);
+
+/// A format loaded by a function of the embedder, which turns the path and
+/// the content of a file into documents that are then split like the ones of
+/// the built-in formats.
+#[derive(Clone)]
+pub struct LoaderEntry {
+  name: String,
+  mime_types: Vec<String>,
+  extensions: Vec<String>,
+  priority: i32,
+  sniff: Option<Arc<SniffFn>>,
+  splitter: SplitterKind,
+  load: Arc<LoadFn>,
+}
+
+impl LoaderEntry {
+  pub fn new<S, F>(name: S, load: F) -> Self
+  where
+    S: Into<String>,
+    F: Fn(&str, &[u8], &DocOptions) -> LoaderResult<Vec<Document>> + Send + Sync + 'static,
+  {
+    Self {
+      name: name.into(),
+      mime_types: vec![],
+      extensions: vec![],
+      priority: 0,
+      sniff: None,
+      splitter: SplitterKind::Token,
+      load: Arc::new(load),
+    }
+  }
+
+  /// MIME type of the files of the format, as detected from their content
+  pub fn with_mime_type<S: Into<String>>(mut self, mime_type: S) -> Self {
+    self.mime_types.push(mime_type.into().to_lowercase());
+    self
+  }
+
+  /// Extension of the files of the format, without the leading dot
+  pub fn with_extension<S: Into<String>>(mut self, extension: S) -> Self {
+    self
+      .extensions
+      .push(extension.into().trim_start_matches('.').to_lowercase());
+    self
+  }
+
+  /// Entries with a higher priority are tried first, the most recently
+  /// registered one wins among entries of the same priority.
+  pub fn with_priority(mut self, priority: i32) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  /// Checks the first 4 KiB of a file. An entry without MIME types and
+  /// extensions matches every file accepted by it, otherwise a file must
+  /// match both.
+  pub fn with_sniff<F>(mut self, sniff: F) -> Self
+  where
+    F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+  {
+    self.sniff = Some(Arc::new(sniff));
+    self
+  }
+
+  /// Splitter of the documents of the format, defaults to the token splitter
+  pub fn with_splitter(mut self, splitter: SplitterKind) -> Self {
+    self.splitter = splitter;
+    self
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn matches(&self, mime_type: Option<&str>, extensions: &[&str], head: &[u8]) -> bool {
+    let keyed = !self.mime_types.is_empty() || !self.extensions.is_empty();
+    let key_matches = mime_type.is_some_and(|mime| self.mime_types.iter().any(|m| m == mime))
+      || extensions
+        .iter()
+        .any(|extension| self.extensions.iter().any(|e| e == extension));
+    (!keyed || key_matches) && self.sniff.as_ref().is_none_or(|sniff| sniff(head))
+  }
+
+  pub(crate) fn load(
+    &self,
+    file_path: &str,
+    doc: &[u8],
+    options: &DocOptions,
+  ) -> LoaderResult<Vec<Document>> {
+    (self.load)(file_path, doc, options)
+  }
+
+  pub(crate) fn splitter(&self) -> SplitterKind {
+    self.splitter
+  }
+}
+
+/// Loaders of formats added by the embedder and splitters replacing the one
+/// of a format. Registered loaders are tried before the built-in formats, so
+/// they can also take over one of them.
+#[derive(Clone, Default)]
+pub struct LoaderRegistry {
+  entries: Vec<LoaderEntry>,
+  splitters: HashMap<String, SplitterKind>,
+}
+
+impl LoaderRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the registry used for documents parsed without a registry of
+  /// their own, see [`DocOptions::with_registry`].
+  pub fn global() -> Arc<LoaderRegistry> {
+    GLOBAL
+      .read()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone()
+  }
+
+  /// Updates the global registry, documents that are being parsed keep
+  /// using the registry they started with.
+  pub fn update_global(update: impl FnOnce(&mut LoaderRegistry)) {
+    let mut global = GLOBAL.write().unwrap_or_else(PoisonError::into_inner);
+    update(Arc::make_mut(&mut global));
+  }
+
+  pub fn register(&mut self, entry: LoaderEntry) {
+    self.entries.push(entry);
+  }
+
+  /// Removes the entries named `name`, returns whether there were any.
+  pub fn unregister(&mut self, name: &str) -> bool {
+    let len = self.entries.len();
+    self.entries.retain(|entry| entry.name != name);
+    self.entries.len() != len
+  }
+
+  /// Splits the documents of a format, named by its extension or MIME type,
+  /// with `splitter` instead of the splitter of the format. This applies to
+  /// built-in and registered formats alike.
+  pub fn set_splitter<S: Into<String>>(&mut self, format: S, splitter: SplitterKind) {
+    let format = format.into();
+    self
+      .splitters
+      .insert(format.trim_start_matches('.').to_lowercase(), splitter);
+  }
+
+  /// Returns the entry with the highest priority matching a file.
+  pub(crate) fn find(
+    &self,
+    mime_type: Option<&str>,
+    extensions: &[&str],
+    head: &[u8],
+  ) -> Option<&LoaderEntry> {
+    self
+      .entries
+      .iter()
+      .filter(|entry| entry.matches(mime_type, extensions, head))
+      // the last maximum is the most recently registered one
+      .max_by_key(|entry| entry.priority)
+  }
+
+  /// Returns the splitter set for the first of `formats` that has one.
+  pub(crate) fn splitter(&self, formats: &[&str]) -> Option<SplitterKind> {
+    formats
+      .iter()
+      .find_map(|format| self.splitters.get(&format.to_lowercase()).copied())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn find(
+    registry: &LoaderRegistry,
+    mime: Option<&str>,
+    extension: &str,
+    head: &[u8],
+  ) -> Option<String> {
+    registry
+      .find(mime, &[extension], head)
+      .map(|entry| entry.name().to_string())
+  }
+
+  fn entry(name: &str) -> LoaderEntry {
+    let name = name.to_string();
+    LoaderEntry::new(name.clone(), move |_, _, _| Ok(vec![Document::new(&name)]))
+  }
+
+  #[test]
+  fn test_find_entry() {
+    let mut registry = LoaderRegistry::new();
+    registry.register(entry("log").with_extension(".LOG"));
+    registry.register(entry("pdf").with_mime_type("application/pdf"));
+    registry.register(
+      entry("sniffed")
+        .with_sniff(|head| head.starts_with(b"#!log"))
+        .with_priority(-1),
+    );
+    registry.register(
+      entry("guarded")
+        .with_extension("log")
+        .with_sniff(|head| head.starts_with(b"#!log"))
+        .with_priority(1),
+    );
+
+    assert_eq!(
+      find(&registry, None, "log", b"text").as_deref(),
+      Some("log")
+    );
+    assert_eq!(
+      find(&registry, None, "log", b"#!log").as_deref(),
+      Some("guarded")
+    );
+    assert_eq!(
+      find(&registry, None, "txt", b"#!log").as_deref(),
+      Some("sniffed")
+    );
+    assert_eq!(
+      find(&registry, Some("application/pdf"), "bin", b"%PDF").as_deref(),
+      Some("pdf")
+    );
+    assert_eq!(find(&registry, None, "txt", b"text"), None);
+
+    // the most recently registered entry wins among equal priorities
+    registry.register(entry("log2").with_extension("log"));
+    assert_eq!(
+      find(&registry, None, "log", b"text").as_deref(),
+      Some("log2")
+    );
+    assert!(registry.unregister("log2"));
+    assert_eq!(
+      find(&registry, None, "log", b"text").as_deref(),
+      Some("log")
+    );
+  }
+}
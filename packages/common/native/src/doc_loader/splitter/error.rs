@@ -24,6 +24,9 @@ pub enum TextSplitterError {
   #[error("Invalid chunk overlap and size")]
   InvalidSplitterOptions,
 
+  #[error("Chunk not found in the split text")]
+  ChunkNotFound,
+
   #[error("Error: {0}")]
   OtherError(String),
 }
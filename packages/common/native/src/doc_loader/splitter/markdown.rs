@@ -23,14 +23,260 @@ impl MarkdownSplitter {
   }
 }
 
+/// Parses an ATX heading line into its level and plain text, dropping inline
+/// html such as bookmark anchors and the emphasis markers around the text.
+pub fn parse_heading(line: &str) -> Option<(usize, String)> {
+  let line = line.trim_start();
+  let level = line.chars().take_while(|c| *c == '#').count();
+  if !(1..=6).contains(&level) || !line[level..].starts_with(' ') {
+    return None;
+  }
+  Some((level, heading_text(&line[level..])))
+}
+
+fn heading_text(text: &str) -> String {
+  let mut plain = String::new();
+  let mut in_tag = false;
+  for c in text.chars() {
+    match c {
+      '<' => in_tag = true,
+      '>' if in_tag => in_tag = false,
+      c if !in_tag => plain.push(c),
+      _ => {}
+    }
+  }
+  plain
+    .trim()
+    .trim_end_matches('#')
+    .trim()
+    .trim_matches(['*', '_'])
+    .trim()
+    .to_string()
+}
+
+/// Collects the ATX and Setext headings of `text` as (byte offset of the
+/// heading line, level, text), skipping fenced code blocks.
+fn collect_headings(text: &str) -> Vec<(usize, usize, String)> {
+  let mut headings = vec![];
+  let mut fence: Option<&str> = None;
+  // the previous line, if it could be the text of a Setext heading
+  let mut paragraph: Option<(usize, &str)> = None;
+  let mut offset = 0;
+  for line in text.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    let content = line.trim_end();
+    let trimmed = content.trim_start();
+    let indented = content.len() - trimmed.len() >= 4;
+
+    if let Some(marker) = fence {
+      if trimmed.starts_with(marker) {
+        fence = None;
+      }
+      continue;
+    }
+    if !indented && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+      fence = Some(&trimmed[..3]);
+      paragraph = None;
+      continue;
+    }
+
+    if !indented {
+      if let Some((level, heading)) = parse_heading(trimmed) {
+        headings.push((start, level, heading));
+        paragraph = None;
+        continue;
+      }
+      if let Some((paragraph_start, paragraph_text)) = paragraph {
+        let level = if trimmed.chars().all(|c| c == '=') {
+          1
+        } else if trimmed.chars().all(|c| c == '-') {
+          2
+        } else {
+          0
+        };
+        if level > 0 && !trimmed.is_empty() {
+          headings.push((paragraph_start, level, heading_text(paragraph_text)));
+          paragraph = None;
+          continue;
+        }
+      }
+    }
+
+    paragraph = (!trimmed.is_empty() && !indented).then_some((start, trimmed));
+  }
+  headings
+}
+
+/// Updates `stack`, the levels and texts of the headings enclosing the text
+/// before `text`, to the headings enclosing the end of `text`.
+pub fn update_heading_stack(stack: &mut Vec<(usize, String)>, text: &str) {
+  for (_, level, heading) in collect_headings(text) {
+    stack.retain(|(l, _)| *l < level);
+    stack.push((level, heading));
+  }
+}
+
 impl TextSplitter for MarkdownSplitter {
   fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_text_indices(text)?
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
     let chunk_config = ChunkConfig::try_from(&self.splitter_options)?;
     Ok(
       text_splitter::MarkdownSplitter::new(chunk_config)
-        .chunks(text)
-        .map(|x| x.to_string())
+        .chunk_indices(text)
+        .map(|(offset, x)| (offset, x.to_string()))
         .collect(),
     )
   }
+
+  /// Same as the default implementation, additionally recording the
+  /// `heading_path` enclosing each chunk and the `line_start`/`line_end` it
+  /// covers. A `heading_path` or `line_start` set by the loader is kept as is
+  /// and used as base respectively, a `heading_stack` of `[level, heading]`
+  /// pairs enclosing the text, such as the headings of the earlier windows of
+  /// a streamed file, starts the `heading_path`.
+  fn create_documents(
+    &self,
+    text: &[String],
+    metadata: &[HashMap<String, Value>],
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    let mut metadata = metadata.to_vec();
+    if metadata.is_empty() {
+      metadata = vec![HashMap::new(); text.len()];
+    }
+
+    if text.len() != metadata.len() {
+      return Err(TextSplitterError::MetadataTextMismatch);
+    }
+
+    let mut documents: Vec<Document> = Vec::new();
+    for (text, mut metadata) in text.iter().zip(metadata) {
+      let base = metadata
+        .get("start")
+        .and_then(Value::as_u64)
+        .unwrap_or_default() as usize;
+      let base_line = metadata
+        .get("line_start")
+        .and_then(Value::as_u64)
+        .unwrap_or(1) as usize;
+      let headings = collect_headings(text);
+
+      let mut line = base_line;
+      let mut cursor = 0;
+      let mut heading_path: Vec<(usize, String)> = metadata
+        .remove("heading_stack")
+        .and_then(|stack| serde_json::from_value(stack).ok())
+        .unwrap_or_default();
+      let mut next_heading = headings.iter().peekable();
+      for (offset, chunk) in self.split_text_indices(text)? {
+        while let Some((_, level, heading)) = next_heading.next_if(|(at, ..)| *at <= offset) {
+          heading_path.retain(|(l, _)| l < level);
+          heading_path.push((*level, heading.clone()));
+        }
+        line += text[cursor..offset].matches('\n').count();
+        cursor = offset;
+
+        let start = base + offset;
+        let mut metadata = metadata.clone();
+        metadata.insert("start".to_string(), Value::from(start));
+        metadata.insert("end".to_string(), Value::from(start + chunk.len()));
+        metadata.insert("line_start".to_string(), Value::from(line));
+        metadata.insert(
+          "line_end".to_string(),
+          Value::from(line + chunk.trim_end().matches('\n').count()),
+        );
+        metadata
+          .entry("heading_path".to_string())
+          .or_insert_with(|| {
+            Value::from(
+              heading_path
+                .iter()
+                .map(|(_, heading)| heading.clone())
+                .collect::<Vec<_>>(),
+            )
+          });
+        documents.push(Document::new(chunk).with_metadata(metadata));
+      }
+    }
+
+    Ok(documents)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_markdown_headings() {
+    let markdown = "# Intro\n\ntext\n\nScope\n-----\n\n```md\n# not a heading\n```\n\n### Details\n\nmore\n\nOther\n=====\n";
+    assert_eq!(
+      collect_headings(markdown)
+        .into_iter()
+        .map(|(offset, level, heading)| (&markdown[offset..offset + 5], level, heading))
+        .collect::<Vec<_>>(),
+      vec![
+        ("# Int", 1, "Intro".to_string()),
+        ("Scope", 2, "Scope".to_string()),
+        ("### D", 3, "Details".to_string()),
+        ("Other", 1, "Other".to_string()),
+      ]
+    );
+
+    let splitter = MarkdownSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(8)
+        .with_chunk_overlap(0),
+    );
+    let documents = splitter
+      .create_documents(&[markdown.to_string()], &[])
+      .unwrap();
+    assert!(documents.len() > 1);
+    let lines = markdown.lines().collect::<Vec<_>>();
+    for doc in documents.iter() {
+      let line_start = doc.metadata["line_start"].as_u64().unwrap() as usize;
+      let line_end = doc.metadata["line_end"].as_u64().unwrap() as usize;
+      assert_eq!(
+        lines[line_start - 1..line_end].join("\n"),
+        doc.page_content.trim()
+      );
+    }
+    let more = documents
+      .iter()
+      .find(|doc| doc.page_content.contains("more"))
+      .unwrap();
+    assert_eq!(
+      more.metadata["heading_path"],
+      Value::from(vec!["Intro", "Scope", "Details"])
+    );
+  }
+
+  #[test]
+  fn test_heading_stack() {
+    let mut stack = vec![(1, "Guide".to_string()), (2, "Setup".to_string())];
+    let metadata = HashMap::from([(
+      "heading_stack".to_string(),
+      serde_json::to_value(&stack).unwrap(),
+    )]);
+    let documents = MarkdownSplitter::default()
+      .create_documents(&["Install it first.\n".to_string()], &[metadata])
+      .unwrap();
+    assert_eq!(
+      documents[0].metadata["heading_path"],
+      Value::from(vec!["Guide", "Setup"])
+    );
+    assert!(!documents[0].metadata.contains_key("heading_stack"));
+
+    update_heading_stack(&mut stack, "text\n\n## Usage\n\n```\n# comment\n```\n");
+    assert_eq!(stack, [(1, "Guide".to_string()), (2, "Usage".to_string())]);
+  }
 }
@@ -4,21 +4,52 @@
 mod error;
 mod markdown;
 mod options;
+mod sentence;
+mod structured;
+mod table;
 mod token;
+mod transcript;
 
 use std::collections::HashMap;
 
 pub use error::TextSplitterError;
-pub use markdown::MarkdownSplitter;
-use options::SplitterOptions;
+pub use markdown::{parse_heading, update_heading_stack, MarkdownSplitter};
+pub use options::SplitterOptions;
+pub use sentence::SentenceSplitter;
 use serde_json::Value;
+pub use structured::StructuredSplitter;
+pub use table::TableSplitter;
 pub use token::TokenSplitter;
+pub use transcript::TranscriptSplitter;
 
 use super::*;
 
 pub trait TextSplitter: Send + Sync {
   fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError>;
 
+  /// Splits `text` like [`TextSplitter::split_text`], additionally returning
+  /// the byte offset of every chunk inside `text`.
+  ///
+  /// The default implementation locates each chunk by searching forward from
+  /// the previous one and fails with [`TextSplitterError::ChunkNotFound`] if a
+  /// chunk is not part of `text`. Splitters that know their offsets or rewrite
+  /// their chunks should override it.
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
+    let mut cursor = 0;
+    self
+      .split_text(text)?
+      .into_iter()
+      .map(|chunk| {
+        let offset = text[cursor..]
+          .find(&chunk)
+          .map(|i| cursor + i)
+          .ok_or(TextSplitterError::ChunkNotFound)?;
+        cursor = offset + chunk.chars().next().map_or(0, char::len_utf8);
+        Ok((offset, chunk))
+      })
+      .collect()
+  }
+
   fn split_documents(&self, documents: &[Document]) -> Result<Vec<Document>, TextSplitterError> {
     let mut texts: Vec<String> = Vec::new();
     let mut metadata: Vec<HashMap<String, Value>> = Vec::new();
@@ -30,6 +61,17 @@ pub trait TextSplitter: Send + Sync {
     self.create_documents(&texts, &metadata)
   }
 
+  /// Splits `document`, whose text was parsed into `value` by the
+  /// `StructuredLoader`. The default implementation splits the text of the
+  /// document like [`TextSplitter::split_documents`].
+  fn split_parsed(
+    &self,
+    document: Document,
+    _value: &Value,
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    self.split_documents(&[document])
+  }
+
   fn create_documents(
     &self,
     text: &[String],
@@ -46,9 +88,19 @@ pub trait TextSplitter: Send + Sync {
 
     let mut documents: Vec<Document> = Vec::new();
     for i in 0..text.len() {
-      let chunks = self.split_text(&text[i])?;
-      for chunk in chunks {
-        let document = Document::new(chunk).with_metadata(metadata[i].clone());
+      // offsets are relative to the text the loader extracted, loaders that
+      // emit several documents record where each of them starts
+      let base = metadata[i]
+        .get("start")
+        .and_then(Value::as_u64)
+        .unwrap_or_default() as usize;
+      let chunks = self.split_text_indices(&text[i])?;
+      for (offset, chunk) in chunks {
+        let start = base + offset;
+        let mut metadata = metadata[i].clone();
+        metadata.insert("start".to_string(), Value::from(start));
+        metadata.insert("end".to_string(), Value::from(start + chunk.len()));
+        let document = Document::new(chunk).with_metadata(metadata);
         documents.push(document);
       }
     }
@@ -56,3 +108,35 @@ pub trait TextSplitter: Send + Sync {
     Ok(documents)
   }
 }
+
+/// Boxed splitters split like the splitter they hold, including the methods
+/// it overrides.
+impl TextSplitter for Box<dyn TextSplitter> {
+  fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    (**self).split_text(text)
+  }
+
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
+    (**self).split_text_indices(text)
+  }
+
+  fn split_documents(&self, documents: &[Document]) -> Result<Vec<Document>, TextSplitterError> {
+    (**self).split_documents(documents)
+  }
+
+  fn split_parsed(
+    &self,
+    document: Document,
+    value: &Value,
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    (**self).split_parsed(document, value)
+  }
+
+  fn create_documents(
+    &self,
+    text: &[String],
+    metadata: &[HashMap<String, Value>],
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    (**self).create_documents(text, metadata)
+  }
+}
@@ -61,6 +61,18 @@ impl SplitterOptions {
     self
   }
 
+  /// Returns the tokenizer chunk sizes are measured with.
+  pub fn get_bpe(&self) -> Result<CoreBPE, TextSplitterError> {
+    if !self.encoding_name.is_empty() {
+      let tokenizer = SplitterOptions::get_tokenizer_from_str(&self.encoding_name)
+        .ok_or(TextSplitterError::TokenizerNotFound)?;
+
+      get_bpe_from_tokenizer(tokenizer).map_err(|_| TextSplitterError::InvalidTokenizer)
+    } else {
+      get_bpe_from_model(&self.model_name).map_err(|_| TextSplitterError::InvalidModel)
+    }
+  }
+
   pub fn get_tokenizer_from_str(s: &str) -> Option<Tokenizer> {
     match s.to_lowercase().as_str() {
       "o200k_base" => Some(Tokenizer::O200kBase),
@@ -78,18 +90,9 @@ impl TryFrom<&SplitterOptions> for ChunkConfig<CoreBPE> {
   type Error = TextSplitterError;
 
   fn try_from(options: &SplitterOptions) -> Result<Self, Self::Error> {
-    let tk = if !options.encoding_name.is_empty() {
-      let tokenizer = SplitterOptions::get_tokenizer_from_str(&options.encoding_name)
-        .ok_or(TextSplitterError::TokenizerNotFound)?;
-
-      get_bpe_from_tokenizer(tokenizer).map_err(|_| TextSplitterError::InvalidTokenizer)?
-    } else {
-      get_bpe_from_model(&options.model_name).map_err(|_| TextSplitterError::InvalidModel)?
-    };
-
     Ok(
       ChunkConfig::new(options.chunk_size)
-        .with_sizer(tk)
+        .with_sizer(options.get_bpe()?)
         .with_trim(options.trim_chunks)
         .with_overlap(options.chunk_overlap)?,
     )
@@ -0,0 +1,284 @@
+use std::{
+  fmt,
+  ops::Range,
+  sync::{Arc, OnceLock},
+};
+
+use text_splitter::ChunkConfig;
+use tiktoken_rs::CoreBPE;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::*;
+
+/// A paragraph, sentence or part of a sentence, the smallest piece of text
+/// the splitter keeps together.
+struct Unit {
+  range: Range<usize>,
+  tokens: usize,
+}
+
+/// Returns the range of `text[range]` without its surrounding whitespace, or
+/// `None` if nothing is left.
+fn trim_range(text: &str, range: Range<usize>) -> Option<Range<usize>> {
+  let slice = &text[range.clone()];
+  let start = range.start + (slice.len() - slice.trim_start().len());
+  let end = range.end - (slice.len() - slice.trim_end().len());
+  (start < end).then_some(start..end)
+}
+
+/// Returns the ranges of the paragraphs of `text`, which are separated by
+/// blank lines.
+fn paragraphs(text: &str) -> Vec<Range<usize>> {
+  let mut paragraphs = vec![];
+  let mut start = 0;
+  let mut offset = 0;
+  for line in text.split_inclusive('\n') {
+    offset += line.len();
+    if line.trim().is_empty() {
+      paragraphs.extend(trim_range(text, start..offset));
+      start = offset;
+    }
+  }
+  paragraphs.extend(trim_range(text, start..text.len()));
+  paragraphs
+}
+
+/// Returns the ranges of the sentences of `text` following the Unicode
+/// sentence boundaries (UAX #29), which also cover CJK punctuation such as
+/// `。` or `！`.
+fn sentences(text: &str) -> Vec<Range<usize>> {
+  // text extracted from PDFs wraps lines inside of sentences, and UAX #29
+  // always breaks after a line break. Both are one byte long, so replacing
+  // them keeps the offsets intact.
+  let text = text.replace(['\n', '\r'], " ");
+  text
+    .split_sentence_bound_indices()
+    .filter_map(|(offset, sentence)| trim_range(&text, offset..offset + sentence.len()))
+    .collect()
+}
+
+/// The tokenizer and the splitter of sentences larger than the chunk size,
+/// which splits them along words.
+pub(super) struct Sizer {
+  bpe: CoreBPE,
+  pub(super) words: text_splitter::TextSplitter<CoreBPE>,
+}
+
+impl Sizer {
+  pub(super) fn new(options: &SplitterOptions) -> Result<Sizer, TextSplitterError> {
+    let chunk_config = ChunkConfig::try_from(options)?.with_overlap(0)?;
+    Ok(Sizer {
+      bpe: options.get_bpe()?,
+      words: text_splitter::TextSplitter::new(chunk_config),
+    })
+  }
+
+  pub(super) fn tokens(&self, text: &str) -> usize {
+    self.bpe.encode_ordinary(text).len()
+  }
+}
+
+impl fmt::Debug for Sizer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Sizer").finish_non_exhaustive()
+  }
+}
+
+/// Splits text along paragraphs and sentences. Paragraphs that fit the chunk
+/// size are kept whole and consecutive paragraphs or sentences are grouped
+/// into chunks of at most `chunk_size` tokens, a sentence is only split if it
+/// is larger than that on its own.
+///
+/// The overlap is made of whole sentences or paragraphs repeated from the end
+/// of the previous chunk, as long as they fit `chunk_overlap`.
+#[derive(Debug, Clone)]
+pub struct SentenceSplitter {
+  splitter_options: SplitterOptions,
+  /// Built on first use and shared by the texts split afterwards
+  sizer: OnceLock<Arc<Sizer>>,
+}
+
+impl Default for SentenceSplitter {
+  fn default() -> Self {
+    SentenceSplitter::new(SplitterOptions::default())
+  }
+}
+
+impl SentenceSplitter {
+  pub fn new(options: SplitterOptions) -> SentenceSplitter {
+    SentenceSplitter {
+      splitter_options: options,
+      sizer: OnceLock::new(),
+    }
+  }
+
+  fn sizer(&self) -> Result<&Sizer, TextSplitterError> {
+    if let Some(sizer) = self.sizer.get() {
+      return Ok(sizer);
+    }
+    let sizer = Sizer::new(&self.splitter_options)?;
+    Ok(self.sizer.get_or_init(|| Arc::new(sizer)))
+  }
+
+  fn units(&self, text: &str, sizer: &Sizer) -> Vec<Unit> {
+    let chunk_size = self.splitter_options.chunk_size;
+    let mut units = vec![];
+    for paragraph in paragraphs(text) {
+      let paragraph_tokens = sizer.tokens(&text[paragraph.clone()]);
+      if paragraph_tokens <= chunk_size {
+        units.push(Unit {
+          range: paragraph,
+          tokens: paragraph_tokens,
+        });
+        continue;
+      }
+
+      for sentence in sentences(&text[paragraph.clone()]) {
+        let range = paragraph.start + sentence.start..paragraph.start + sentence.end;
+        let sentence_tokens = sizer.tokens(&text[range.clone()]);
+        if sentence_tokens <= chunk_size {
+          units.push(Unit {
+            range,
+            tokens: sentence_tokens,
+          });
+          continue;
+        }
+
+        for (offset, part) in sizer.words.chunk_indices(&text[range.clone()]) {
+          let start = range.start + offset;
+          units.push(Unit {
+            range: start..start + part.len(),
+            tokens: sizer.tokens(part),
+          });
+        }
+      }
+    }
+    units
+  }
+}
+
+impl TextSplitter for SentenceSplitter {
+  fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_text_indices(text)?
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
+    let sizer = self.sizer()?;
+    let tokens = |text: &str| sizer.tokens(text);
+    let chunk_size = self.splitter_options.chunk_size;
+    let units = self.units(text, sizer);
+
+    let mut chunks = vec![];
+    let mut first = 0;
+    while first < units.len() {
+      let mut last = first + 1;
+      let mut total = units[first].tokens;
+      while last < units.len() && total + units[last].tokens <= chunk_size {
+        total += units[last].tokens;
+        last += 1;
+      }
+      // the sum of the units only estimates the tokens of the joined text,
+      // which is what the chunk size limits
+      let range = |last: usize| units[first].range.start..units[last - 1].range.end;
+      while last > first + 1 && tokens(&text[range(last)]) > chunk_size {
+        last -= 1;
+      }
+      chunks.push((range(last).start, text[range(last)].to_string()));
+      if last == units.len() {
+        break;
+      }
+
+      let mut next = last;
+      let mut overlap = 0;
+      while next > first + 1
+        && overlap + units[next - 1].tokens <= self.splitter_options.chunk_overlap
+      {
+        overlap += units[next - 1].tokens;
+        next -= 1;
+      }
+      // the overlap must leave room for the unit that follows it
+      if overlap + units[last].tokens > chunk_size {
+        next = last;
+      }
+      first = next;
+    }
+    Ok(chunks)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sentence_splitter() {
+    let text = "The first sentence is here. The second one\nwraps a line! Is this the third?\n\n\
+                Another paragraph follows. It has two sentences.\n\n\
+                日本語の文です。二つ目の文です！三つ目？";
+    assert_eq!(
+      sentences(text)
+        .into_iter()
+        .map(|range| &text[range])
+        .take(3)
+        .collect::<Vec<_>>(),
+      [
+        "The first sentence is here.",
+        "The second one\nwraps a line!",
+        "Is this the third?"
+      ]
+    );
+    assert_eq!(
+      sentences("日本語の文です。二つ目の文です！三つ目？").len(),
+      3
+    );
+
+    let bpe = SplitterOptions::default().get_bpe().unwrap();
+    for chunk_size in [8, 16, 32] {
+      let splitter = SentenceSplitter::new(
+        SplitterOptions::default()
+          .with_chunk_size(chunk_size)
+          .with_chunk_overlap(0),
+      );
+      let chunks = splitter.split_text_indices(text).unwrap();
+      assert!(chunks.len() > 1);
+      for (offset, chunk) in chunks {
+        assert_eq!(&text[offset..offset + chunk.len()], chunk);
+        assert!(bpe.encode_ordinary(&chunk).len() <= chunk_size);
+      }
+    }
+
+    // sentences are not cut when they fit the chunk size
+    let splitter = SentenceSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(32)
+        .with_chunk_overlap(0),
+    );
+    let chunks = splitter.split_text(text).unwrap();
+    assert!(chunks
+      .iter()
+      .all(|chunk| chunk.ends_with(['.', '!', '?', '。', '！', '？'])));
+  }
+
+  #[test]
+  fn test_sentence_overlap() {
+    let text = "One. Two. Three. Four. Five. Six.";
+    let splitter = SentenceSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(6)
+        .with_chunk_overlap(2),
+    );
+    let chunks = splitter.split_text(text).unwrap();
+    assert!(chunks.len() > 1);
+    // every chunk starts with the last sentence of the previous one
+    for pair in chunks.windows(2) {
+      let last = pair[0].rsplit(". ").next().unwrap();
+      assert!(pair[1].starts_with(last.trim_end_matches('.')));
+    }
+  }
+}
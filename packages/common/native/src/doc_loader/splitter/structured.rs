@@ -0,0 +1,417 @@
+use serde::{
+  ser::{SerializeMap, SerializeSeq},
+  Serialize, Serializer,
+};
+use text_splitter::ChunkConfig;
+
+use super::*;
+
+/// Tokens an entry of an object or array adds besides its key and value, for
+/// separators, indentation and the line break.
+const ENTRY_TOKENS: usize = 2;
+
+#[derive(Debug, Clone, Copy)]
+enum Segment<'a> {
+  Key(&'a str),
+  Index(usize),
+}
+
+/// Returns the JSON pointer (RFC 6901) of `path`.
+fn pointer(path: &[Segment]) -> String {
+  path
+    .iter()
+    .map(|segment| match segment {
+      Segment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+      Segment::Index(index) => format!("/{}", index),
+    })
+    .collect()
+}
+
+/// The content of a chunk, a value or neighbouring entries of an object or
+/// array.
+#[derive(Clone, Copy)]
+enum Content<'a> {
+  Value(&'a Value),
+  Entries(bool, &'a [(Segment<'a>, &'a Value, &'a Size)]),
+}
+
+impl Serialize for Content<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      Self::Value(value) => value.serialize(serializer),
+      Self::Entries(true, entries) => {
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (segment, value, _) in entries.iter() {
+          if let Segment::Key(key) = segment {
+            map.serialize_entry(key, value)?;
+          }
+        }
+        map.end()
+      }
+      Self::Entries(false, entries) => {
+        let mut seq = serializer.serialize_seq(Some(entries.len()))?;
+        for (_, value, _) in entries.iter() {
+          seq.serialize_element(value)?;
+        }
+        seq.end()
+      }
+    }
+  }
+}
+
+/// Content nested under the keys of `path`, so chunks keep the keys leading
+/// to them, array indices are only recorded in the pointer. It is serialized
+/// from the parsed value without copying it.
+struct Wrapped<'a> {
+  path: &'a [Segment<'a>],
+  content: Option<Content<'a>>,
+}
+
+impl Serialize for Wrapped<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let inner = |path| Wrapped {
+      path,
+      content: self.content,
+    };
+    match self.path.split_first() {
+      None => self.content.serialize(serializer),
+      Some((Segment::Key(key), path)) => {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(key, &inner(path))?;
+        map.end()
+      }
+      Some((Segment::Index(_), path)) => {
+        let mut seq = serializer.serialize_seq(Some(1))?;
+        seq.serialize_element(&inner(path))?;
+        seq.end()
+      }
+    }
+  }
+}
+
+/// Estimated number of tokens of a value and of each of its entries, which
+/// is measured once for the whole document from the leaves up.
+struct Size {
+  tokens: usize,
+  entries: Vec<Size>,
+}
+
+impl Size {
+  fn measure(format: DataFormat, value: &Value, tokens: &impl Fn(&str) -> usize) -> Self {
+    let entries: Vec<Size> = match value {
+      Value::Object(map) => map
+        .iter()
+        .map(|(key, value)| {
+          let size = Self::measure(format, value, tokens);
+          Self {
+            tokens: size.tokens + tokens(key),
+            ..size
+          }
+        })
+        .collect(),
+      Value::Array(items) => items
+        .iter()
+        .map(|value| Self::measure(format, value, tokens))
+        .collect(),
+      scalar => {
+        return Self {
+          tokens: tokens(&format.render(scalar)),
+          entries: vec![],
+        }
+      }
+    };
+    Self {
+      tokens: entries
+        .iter()
+        .map(|entry| entry.tokens + ENTRY_TOKENS)
+        .sum::<usize>()
+        + ENTRY_TOKENS,
+      entries,
+    }
+  }
+}
+
+/// Splits JSON, YAML and TOML documents along their objects and arrays.
+/// Values that fit the chunk size are kept whole and neighbouring values are
+/// grouped, every chunk carries the JSON pointer of the value it covers as
+/// `json_pointer`.
+///
+/// The chunks are rendered from the parsed values, so they have no offsets in
+/// the text. Documents that do not parse are split like plain text.
+#[derive(Debug, Clone)]
+pub struct StructuredSplitter {
+  splitter_options: SplitterOptions,
+}
+
+impl Default for StructuredSplitter {
+  fn default() -> Self {
+    StructuredSplitter::new(SplitterOptions::default())
+  }
+}
+
+impl StructuredSplitter {
+  pub fn new(options: SplitterOptions) -> StructuredSplitter {
+    StructuredSplitter {
+      splitter_options: options,
+    }
+  }
+
+  /// Returns the JSON pointers and contents of the chunks of `text`.
+  fn split_structured(
+    &self,
+    text: &str,
+    format: DataFormat,
+  ) -> Result<Vec<(Option<String>, String)>, TextSplitterError> {
+    match format.parse(text) {
+      Ok(value) => self.split_parsed_value(&value, format),
+      Err(_) => Ok(
+        TokenSplitter::new(self.splitter_options.clone())
+          .split_text(text)?
+          .into_iter()
+          .map(|chunk| (None, chunk))
+          .collect(),
+      ),
+    }
+  }
+
+  fn split_parsed_value(
+    &self,
+    value: &Value,
+    format: DataFormat,
+  ) -> Result<Vec<(Option<String>, String)>, TextSplitterError> {
+    let bpe = self.splitter_options.get_bpe()?;
+    let tokens = |text: &str| bpe.encode_ordinary(text).len();
+    let mut chunks = vec![];
+    let size = Size::measure(format, value, &tokens);
+    self.split_value(format, &mut vec![], value, &size, &tokens, &mut chunks);
+
+    // scalars larger than the chunk size cannot be split along the structure
+    let chunk_config = ChunkConfig::try_from(&self.splitter_options)?;
+    let splitter = text_splitter::TextSplitter::new(chunk_config);
+    Ok(
+      chunks
+        .into_iter()
+        .flat_map(|(pointer, chunk)| {
+          if tokens(&chunk) <= self.splitter_options.chunk_size {
+            return vec![(Some(pointer), chunk)];
+          }
+          splitter
+            .chunks(&chunk)
+            .map(|part| (Some(pointer.clone()), part.to_string()))
+            .collect()
+        })
+        .collect(),
+    )
+  }
+
+  fn split_value<'a>(
+    &self,
+    format: DataFormat,
+    path: &mut Vec<Segment<'a>>,
+    value: &'a Value,
+    size: &'a Size,
+    tokens: &impl Fn(&str) -> usize,
+    chunks: &mut Vec<(String, String)>,
+  ) {
+    let entries = match value {
+      Value::Object(map) => map
+        .iter()
+        .zip(&size.entries)
+        .map(|((key, value), size)| (Segment::Key(key), value, size))
+        .collect(),
+      Value::Array(items) => items
+        .iter()
+        .enumerate()
+        .zip(&size.entries)
+        .map(|((index, value), size)| (Segment::Index(index), value, size))
+        .collect(),
+      _ => vec![],
+    };
+    let render = |path: &[Segment], content| format.render(&Wrapped { path, content });
+    // the keys leading to the value are repeated in every chunk
+    let path_tokens = tokens(&render(path, None));
+    let chunk_size = self.splitter_options.chunk_size;
+    if entries.is_empty() || path_tokens + size.tokens <= chunk_size {
+      let rendered = render(path, Some(Content::Value(value)));
+      // the size is an estimate, values that turn out too large are split
+      if entries.is_empty() || tokens(&rendered) <= chunk_size {
+        chunks.push((pointer(path), rendered));
+        return;
+      }
+    }
+
+    let budget = chunk_size.saturating_sub(path_tokens);
+    let object = value.is_object();
+    let mut group: Vec<(Segment<'a>, &'a Value, &'a Size)> = vec![];
+    let mut group_tokens = 0;
+    for (segment, entry, entry_size) in entries {
+      let entry_tokens = entry_size.tokens + ENTRY_TOKENS;
+      if entry_tokens > budget {
+        self.flush(format, path, object, &group, tokens, chunks);
+        group.clear();
+        group_tokens = 0;
+        path.push(segment);
+        self.split_value(format, path, entry, entry_size, tokens, chunks);
+        path.pop();
+        continue;
+      }
+      if group_tokens + entry_tokens > budget {
+        self.flush(format, path, object, &group, tokens, chunks);
+        group.clear();
+        group_tokens = 0;
+      }
+      group.push((segment, entry, entry_size));
+      group_tokens += entry_tokens;
+    }
+    self.flush(format, path, object, &group, tokens, chunks);
+  }
+
+  /// Adds a chunk of neighbouring entries of an object or array, which is
+  /// halved if it turns out larger than the chunk size. A single entry is
+  /// split like a value of its own.
+  fn flush<'a>(
+    &self,
+    format: DataFormat,
+    path: &mut Vec<Segment<'a>>,
+    object: bool,
+    group: &[(Segment<'a>, &'a Value, &'a Size)],
+    tokens: &impl Fn(&str) -> usize,
+    chunks: &mut Vec<(String, String)>,
+  ) {
+    let rendered = match group {
+      [] => return,
+      [(segment, value, size)] => {
+        path.push(*segment);
+        self.split_value(format, path, value, size, tokens, chunks);
+        path.pop();
+        return;
+      }
+      group => {
+        let content = Some(Content::Entries(object, group));
+        format.render(&Wrapped { path, content })
+      }
+    };
+    if tokens(&rendered) > self.splitter_options.chunk_size {
+      let (first, second) = group.split_at(group.len() / 2);
+      self.flush(format, path, object, first, tokens, chunks);
+      self.flush(format, path, object, second, tokens, chunks);
+    } else {
+      chunks.push((pointer(path), rendered));
+    }
+  }
+}
+
+impl TextSplitter for StructuredSplitter {
+  /// Splits `text` as JSON, the format of other documents is given by their
+  /// `format` metadata.
+  fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_structured(text, DataFormat::Json)?
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  /// Same as the default implementation, except that chunks have no
+  /// `start`/`end` and carry their `json_pointer` instead.
+  fn create_documents(
+    &self,
+    text: &[String],
+    metadata: &[HashMap<String, Value>],
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    let mut metadata = metadata.to_vec();
+    if metadata.is_empty() {
+      metadata = vec![HashMap::new(); text.len()];
+    }
+
+    if text.len() != metadata.len() {
+      return Err(TextSplitterError::MetadataTextMismatch);
+    }
+
+    let mut documents: Vec<Document> = Vec::new();
+    for (text, metadata) in text.iter().zip(metadata) {
+      let chunks = self.split_structured(text, format_of(&metadata))?;
+      documents.extend(with_pointers(chunks, metadata));
+    }
+
+    Ok(documents)
+  }
+
+  fn split_parsed(
+    &self,
+    document: Document,
+    value: &Value,
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    let chunks = self.split_parsed_value(value, format_of(&document.metadata))?;
+    Ok(with_pointers(chunks, document.metadata))
+  }
+}
+
+/// Returns the format of a document given by its `format` metadata.
+fn format_of(metadata: &HashMap<String, Value>) -> DataFormat {
+  metadata
+    .get("format")
+    .and_then(Value::as_str)
+    .and_then(DataFormat::from_extension)
+    .unwrap_or(DataFormat::Json)
+}
+
+/// Turns chunks into documents with the metadata of the document they are
+/// split from, besides its offsets.
+fn with_pointers(
+  chunks: Vec<(Option<String>, String)>,
+  mut metadata: HashMap<String, Value>,
+) -> Vec<Document> {
+  metadata.remove("start");
+  metadata.remove("end");
+  chunks
+    .into_iter()
+    .map(|(pointer, chunk)| {
+      let mut metadata = metadata.clone();
+      if let Some(pointer) = pointer {
+        metadata.insert("json_pointer".to_string(), Value::from(pointer));
+      }
+      Document::new(chunk).with_metadata(metadata)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_structured_splitter() {
+    let json = include_str!("../../../fixtures/sample.json");
+    let splitter = StructuredSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(64)
+        .with_chunk_overlap(0),
+    );
+    let metadata = HashMap::from([("format".to_string(), Value::from("json"))]);
+    let documents = splitter
+      .create_documents(&[json.to_string()], &[metadata])
+      .unwrap();
+    assert!(documents.len() > 1);
+
+    let value: Value = serde_json::from_str(json).unwrap();
+    let bpe = SplitterOptions::default().get_bpe().unwrap();
+    for doc in &documents {
+      // chunks are valid JSON nesting the value under the keys leading to it
+      let pointer = doc.metadata["json_pointer"].as_str().unwrap();
+      assert!(value.pointer(pointer).is_some());
+      assert!(serde_json::from_str::<Value>(&doc.page_content).is_ok());
+      assert!(bpe.encode_ordinary(&doc.page_content).len() <= 64);
+      assert!(!doc.metadata.contains_key("start"));
+    }
+
+    let pointers = documents
+      .iter()
+      .map(|doc| doc.metadata["json_pointer"].as_str().unwrap())
+      .collect::<Vec<_>>();
+    assert!(pointers.contains(&"/servers/0"));
+    assert!(pointers.iter().any(|p| p.starts_with("/database")));
+  }
+}
@@ -0,0 +1,259 @@
+use std::{
+  ops::Range,
+  sync::{Arc, OnceLock},
+};
+
+use super::{sentence::Sizer, *};
+
+/// A chunk with the byte range and the numbers of the rows it covers.
+type RowsChunk = (Range<usize>, Range<usize>, String);
+
+/// Rows waiting to be grouped into a chunk, with their byte ranges.
+type Group<'a> = Vec<(Range<usize>, &'a str)>;
+
+/// Splits markdown tables into groups of rows that fit the chunk size, every
+/// chunk repeats the header row so it can be understood on its own. A row that
+/// does not fit the chunk size together with the header is split along words
+/// into chunks of its own, without the header.
+#[derive(Debug, Clone)]
+pub struct TableSplitter {
+  splitter_options: SplitterOptions,
+  /// Built on first use and shared by the tables split afterwards
+  sizer: OnceLock<Arc<Sizer>>,
+}
+
+impl Default for TableSplitter {
+  fn default() -> Self {
+    TableSplitter::new(SplitterOptions::default())
+  }
+}
+
+impl TableSplitter {
+  pub fn new(options: SplitterOptions) -> TableSplitter {
+    TableSplitter {
+      splitter_options: options,
+      sizer: OnceLock::new(),
+    }
+  }
+
+  fn sizer(&self) -> Result<&Sizer, TextSplitterError> {
+    if let Some(sizer) = self.sizer.get() {
+      return Ok(sizer);
+    }
+    let sizer = Sizer::new(&self.splitter_options)?;
+    Ok(self.sizer.get_or_init(|| Arc::new(sizer)))
+  }
+
+  /// Returns the chunks together with the byte range of the rows they cover
+  /// and the (1-based) numbers of these rows, not counting the header.
+  fn split_rows(&self, text: &str) -> Result<Vec<RowsChunk>, TextSplitterError> {
+    let sizer = self.sizer()?;
+    let tokens = |text: &str| sizer.tokens(text);
+
+    let mut lines = vec![];
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+      let content = line.trim_end();
+      if !content.is_empty() {
+        lines.push((offset..offset + content.len(), content));
+      }
+      offset += line.len();
+    }
+
+    let header_len = match lines.get(1) {
+      Some((_, separator)) if is_separator(separator) => 2,
+      _ => 0,
+    };
+    let header = lines[..header_len]
+      .iter()
+      .map(|(_, line)| *line)
+      .collect::<Vec<_>>()
+      .join("\n");
+    let budget = self
+      .splitter_options
+      .chunk_size
+      .saturating_sub(tokens(&header));
+
+    let mut chunks = vec![];
+    let mut group: Group = vec![];
+    let mut group_tokens = 0;
+    let mut row = 1;
+    let flush = |chunks: &mut Vec<RowsChunk>, group: &mut Group, row: &mut usize| {
+      let (Some((first, _)), Some((last, _))) = (group.first(), group.last()) else {
+        return;
+      };
+      let range = first.start..last.end;
+      let rows = *row..*row + group.len();
+      *row += group.len();
+      let body = group.drain(..).map(|(_, line)| line).collect::<Vec<_>>();
+      let content = if header.is_empty() {
+        body.join("\n")
+      } else {
+        format!("{}\n{}", header, body.join("\n"))
+      };
+      chunks.push((range, rows, content));
+    };
+    for (range, line) in lines.into_iter().skip(header_len) {
+      let line_tokens = tokens(line) + 1;
+      if line_tokens > budget {
+        flush(&mut chunks, &mut group, &mut row);
+        group_tokens = 0;
+        for (offset, part) in sizer.words.chunk_indices(line) {
+          let start = range.start + offset;
+          chunks.push((start..start + part.len(), row..row + 1, part.to_string()));
+        }
+        row += 1;
+        continue;
+      }
+      if group_tokens + line_tokens > budget {
+        flush(&mut chunks, &mut group, &mut row);
+        group_tokens = 0;
+      }
+      group.push((range, line));
+      group_tokens += line_tokens;
+    }
+    flush(&mut chunks, &mut group, &mut row);
+
+    Ok(chunks)
+  }
+}
+
+fn is_separator(line: &str) -> bool {
+  line.starts_with('|')
+    && line
+      .chars()
+      .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+impl TextSplitter for TableSplitter {
+  fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_rows(text)?
+        .into_iter()
+        .map(|(_, _, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  /// The header repeated in every chunk is not part of the text at the
+  /// returned offset, which is the offset of the first row of the chunk.
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
+    Ok(
+      self
+        .split_rows(text)?
+        .into_iter()
+        .map(|(range, _, chunk)| (range.start, chunk))
+        .collect(),
+    )
+  }
+
+  /// Same as the default implementation, except that `start`/`end` cover the
+  /// rows of each chunk and `row_start`/`row_end` number them.
+  fn create_documents(
+    &self,
+    text: &[String],
+    metadata: &[HashMap<String, Value>],
+  ) -> Result<Vec<Document>, TextSplitterError> {
+    let mut metadata = metadata.to_vec();
+    if metadata.is_empty() {
+      metadata = vec![HashMap::new(); text.len()];
+    }
+
+    if text.len() != metadata.len() {
+      return Err(TextSplitterError::MetadataTextMismatch);
+    }
+
+    let mut documents: Vec<Document> = Vec::new();
+    for (text, metadata) in text.iter().zip(metadata) {
+      let base = metadata
+        .get("start")
+        .and_then(Value::as_u64)
+        .unwrap_or_default() as usize;
+      for (range, rows, chunk) in self.split_rows(text)? {
+        let mut metadata = metadata.clone();
+        metadata.insert("start".to_string(), Value::from(base + range.start));
+        metadata.insert("end".to_string(), Value::from(base + range.end));
+        metadata.insert("row_start".to_string(), Value::from(rows.start));
+        metadata.insert("row_end".to_string(), Value::from(rows.end - 1));
+        documents.push(Document::new(chunk).with_metadata(metadata));
+      }
+    }
+
+    Ok(documents)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_table_splitter() {
+    let table = (1..=100)
+      .map(|i| format!("| {} | row {} |", i, i))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let table = format!("| id | name |\n| --- | --- |\n{}", table);
+
+    let splitter = TableSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(64)
+        .with_chunk_overlap(0),
+    );
+    let documents = splitter
+      .create_documents(std::slice::from_ref(&table), &[])
+      .unwrap();
+    assert!(documents.len() > 1);
+
+    let mut next_row = 1;
+    for doc in documents {
+      assert!(doc
+        .page_content
+        .starts_with("| id | name |\n| --- | --- |\n"));
+      let row_start = doc.metadata["row_start"].as_u64().unwrap();
+      let row_end = doc.metadata["row_end"].as_u64().unwrap();
+      assert_eq!(row_start, next_row);
+      next_row = row_end + 1;
+
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert!(doc.page_content.ends_with(&table[start..end]));
+    }
+    assert_eq!(next_row, 101);
+  }
+
+  #[test]
+  fn test_split_large_row() {
+    let cell = "lorem ipsum dolor sit amet ".repeat(40);
+    let table = format!(
+      "| id | text |\n| --- | --- |\n| 1 | short |\n| 2 | {} |\n| 3 | short |",
+      cell
+    );
+    let chunk_size = 64;
+    let splitter = TableSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(chunk_size)
+        .with_chunk_overlap(0),
+    );
+    let bpe = SplitterOptions::default().get_bpe().unwrap();
+    let documents = splitter
+      .create_documents(std::slice::from_ref(&table), &[])
+      .unwrap();
+    assert!(documents.len() > 3);
+    for doc in &documents {
+      assert!(bpe.encode_ordinary(&doc.page_content).len() <= chunk_size);
+      let start = doc.metadata["start"].as_u64().unwrap() as usize;
+      let end = doc.metadata["end"].as_u64().unwrap() as usize;
+      assert!(doc.page_content.ends_with(&table[start..end]));
+    }
+    // the parts of the large row are numbered like the row
+    let rows = documents
+      .iter()
+      .map(|doc| doc.metadata["row_start"].as_u64().unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(rows.first(), Some(&1));
+    assert_eq!(rows.last(), Some(&3));
+    assert!(rows[1..rows.len() - 1].iter().all(|row| *row == 2));
+  }
+}
@@ -26,11 +26,21 @@ impl TokenSplitter {
 
 impl TextSplitter for TokenSplitter {
   fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_text_indices(text)?
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
     let chunk_config = ChunkConfig::try_from(&self.splitter_options)?;
     Ok(
       text_splitter::TextSplitter::new(chunk_config)
-        .chunks(text)
-        .map(|x| x.to_string())
+        .chunk_indices(text)
+        .map(|(offset, x)| (offset, x.to_string()))
         .collect(),
     )
   }
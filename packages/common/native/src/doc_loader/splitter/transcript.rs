@@ -0,0 +1,155 @@
+use super::*;
+
+/// Merges the cues of subtitles and transcripts into chunks of at most
+/// `chunk_size` tokens, cues are only split if they are larger than that on
+/// their own. Chunks span from the `start_ms` of their first cue to the
+/// `end_ms` of their last one and do not overlap.
+///
+/// Every document is a cue, as emitted by the `SubtitleLoader`. Plain text is
+/// split with every line as a cue.
+#[derive(Debug, Clone)]
+pub struct TranscriptSplitter {
+  splitter_options: SplitterOptions,
+}
+
+impl Default for TranscriptSplitter {
+  fn default() -> Self {
+    TranscriptSplitter::new(SplitterOptions::default())
+  }
+}
+
+impl TranscriptSplitter {
+  pub fn new(options: SplitterOptions) -> TranscriptSplitter {
+    TranscriptSplitter {
+      splitter_options: options,
+    }
+  }
+
+  /// Joins consecutive cues into one document.
+  fn merge(cues: &[&Document]) -> Option<Document> {
+    let (first, last) = (cues.first()?, cues.last()?);
+    let content = cues
+      .iter()
+      .map(|cue| cue.page_content.as_str())
+      .collect::<Vec<_>>()
+      .join("\n");
+    let mut metadata = first.metadata.clone();
+    if let Some(start) = first.metadata.get("start").and_then(Value::as_u64) {
+      metadata.insert(
+        "end".to_string(),
+        Value::from(start as usize + content.len()),
+      );
+    }
+    if let Some(end_ms) = last.metadata.get("end_ms") {
+      metadata.insert("end_ms".to_string(), end_ms.clone());
+    }
+    Some(Document::new(content).with_metadata(metadata))
+  }
+}
+
+impl TextSplitter for TranscriptSplitter {
+  fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+    Ok(
+      self
+        .split_text_indices(text)?
+        .into_iter()
+        .map(|(_, chunk)| chunk)
+        .collect(),
+    )
+  }
+
+  fn split_text_indices(&self, text: &str) -> Result<Vec<(usize, String)>, TextSplitterError> {
+    let mut offset = 0;
+    let mut lines = vec![];
+    for line in text.split_inclusive('\n') {
+      let content = line.trim_end();
+      if !content.is_empty() {
+        let metadata = HashMap::from([("start".to_string(), Value::from(offset))]);
+        lines.push(Document::new(content).with_metadata(metadata));
+      }
+      offset += line.len();
+    }
+    Ok(
+      self
+        .split_documents(&lines)?
+        .into_iter()
+        .map(|chunk| {
+          let start = chunk.metadata.get("start").and_then(Value::as_u64);
+          (start.unwrap_or_default() as usize, chunk.page_content)
+        })
+        .collect(),
+    )
+  }
+
+  fn split_documents(&self, documents: &[Document]) -> Result<Vec<Document>, TextSplitterError> {
+    let bpe = self.splitter_options.get_bpe()?;
+    let tokens = |text: &str| bpe.encode_ordinary(text).len();
+    let chunk_size = self.splitter_options.chunk_size;
+
+    let mut chunks = vec![];
+    let mut group = vec![];
+    let mut group_tokens = 0;
+    for cue in documents {
+      let cue_tokens = tokens(&cue.page_content);
+      if cue_tokens > chunk_size {
+        chunks.extend(Self::merge(&group));
+        group.clear();
+        // long cues are split on their own and keep their times
+        let splitter = TokenSplitter::new(self.splitter_options.clone());
+        chunks.extend(splitter.split_documents(std::slice::from_ref(cue))?);
+        continue;
+      }
+      // the line break joining two cues is counted as a token
+      if !group.is_empty() && group_tokens + 1 + cue_tokens > chunk_size {
+        chunks.extend(Self::merge(&group));
+        group.clear();
+      }
+      group_tokens = if group.is_empty() {
+        cue_tokens
+      } else {
+        group_tokens + 1 + cue_tokens
+      };
+      group.push(cue);
+    }
+    chunks.extend(Self::merge(&group));
+    Ok(chunks)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_transcript_splitter() {
+    let cues = (0..20u64)
+      .map(|i| {
+        Document::new(format!("Cue number {} of the meeting.", i)).with_metadata(HashMap::from([
+          ("start_ms".to_string(), Value::from(i * 1000)),
+          ("end_ms".to_string(), Value::from(i * 1000 + 900)),
+        ]))
+      })
+      .collect::<Vec<_>>();
+    let splitter = TranscriptSplitter::new(
+      SplitterOptions::default()
+        .with_chunk_size(32)
+        .with_chunk_overlap(0),
+    );
+    let chunks = splitter.split_documents(&cues).unwrap();
+    assert!(chunks.len() > 1 && chunks.len() < cues.len());
+
+    let bpe = SplitterOptions::default().get_bpe().unwrap();
+    let mut next_ms = 0;
+    for chunk in &chunks {
+      assert!(bpe.encode_ordinary(&chunk.page_content).len() <= 32);
+      // chunks cover consecutive cues without overlapping
+      let start_ms = chunk.metadata["start_ms"].as_u64().unwrap();
+      let end_ms = chunk.metadata["end_ms"].as_u64().unwrap();
+      assert_eq!(start_ms, next_ms);
+      let lines = chunk.page_content.lines().count() as u64;
+      assert_eq!(end_ms, start_ms + (lines - 1) * 1000 + 900);
+      next_ms = end_ms + 100;
+    }
+    assert_eq!(next_ms, 20_000);
+  }
+}
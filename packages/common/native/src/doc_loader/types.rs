@@ -35,3 +35,22 @@ impl Default for Document {
     }
   }
 }
+
+/// Properties of a whole document, as recorded by the format or detected
+/// from its text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocMetadata {
+  pub title: Option<String>,
+  pub author: Option<String>,
+  pub subject: Option<String>,
+  pub keywords: Option<String>,
+  /// Creation date in RFC 3339 where the format allows it
+  pub created: Option<String>,
+  pub modified: Option<String>,
+  /// BCP 47 tag of the language declared by the document, such as `en-US`,
+  /// or otherwise the ISO 639-1 code of the language detected from its text,
+  /// such as `en`
+  pub language: Option<String>,
+  /// Character encoding text files were decoded from, such as `Shift_JIS`
+  pub encoding: Option<String>,
+}